@@ -3,13 +3,16 @@ use quote::quote;
 
 /// See the `fn call` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
-	let CallDef { pallet_struct, methods } = def;
+	let CallDef { pallet_struct, config_trait, methods } = def;
 
 	// This is a vector of all the callable function names.
 	let fn_name = methods.iter().map(|method| &method.name).collect::<Vec<_>>();
+	// This is a vector of the `Call` enum variant names, i.e. the function names in `CamelCase`.
+	let variant_name =
+		methods.iter().map(|method| crate::utils::camel_case(&method.name)).collect::<Vec<_>>();
 
 	// This is a nested vector of all the arguments for each of the functions in `fn_name`. It does
-	// not include the `self` or `caller: T::AccountId` parameter, which we always assume are the
+	// not include the `self` or `origin: T::RuntimeOrigin` parameter, which we always assume are the
 	// first two parameters to these calls.
 	let args_name = methods
 		.iter()
@@ -23,42 +26,168 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		.map(|method| method.args.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>())
 		.collect::<Vec<_>>();
 
+	// This is a vector of the weight expressions of each of the functions in `fn_name`.
+	let weight = methods.iter().map(|method| &method.weight).collect::<Vec<_>>();
+
+	// All the argument types, used to bound the `Clone` implementation. We can not simply
+	// `#[derive(Clone)]`, since that would require `T` itself to be `Clone`.
+	let all_args_type = args_type.iter().flatten().collect::<Vec<_>>();
+
 	// This quote block creates an `enum Call` which contains all the calls exposed by our pallet,
-	// and the `Dispatch` trait logic to route a `caller` to access those functions.
+	// along with the weight of each call.
 	let dispatch_impl = quote! {
 		// The callable functions exposed by this pallet.
 		//
-		// The parsed function names will be `snake_case`, and that will show up in the enum.
-		#[allow(non_camel_case_types)]
-		pub enum Call<T: Config> {
+		// The parsed function names are `snake_case`, and are converted to `CamelCase` in the enum.
+		// Calls are encoded as the index of the variant, followed by all the arguments.
+		#[derive(macros::Encode, macros::Decode)]
+		pub enum Call<T: #config_trait> {
 			#(
-				#fn_name { #( #args_name: #args_type),* },
+				#variant_name { #( #args_name: #args_type),* },
 			)*
 		}
 
-		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
-		// appropriate function call with all arguments, including the `caller`.
-		impl<T: Config> crate::support::Dispatch for #pallet_struct<T> {
-			type Caller = T::AccountId;
-			type Call = Call<T>;
+		impl<T: #config_trait> Clone for Call<T> where #( #all_args_type: Clone, )* {
+			fn clone(&self) -> Self {
+				match self {
+					#(
+						Self::#variant_name { #( #args_name ),* } => Self::#variant_name {
+							#( #args_name: #args_name.clone() ),*
+						},
+					)*
+				}
+			}
+		}
 
-			fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
-				match call {
+		// The weight of each call, as declared with `#[weight(...)]`. The weight expressions can use
+		// the arguments of the call, by reference.
+		impl<T: #config_trait> crate::support::GetDispatchInfo for Call<T> {
+			fn get_dispatch_info(&self) -> crate::support::DispatchInfo {
+				match self {
 					#(
-						Call::#fn_name { #( #args_name ),* } => {
-							self.#fn_name(
-								// Note that we assume the first argument of every call is the `caller`.
-								caller,
-								#( #args_name ),*
-							)?;
+						#[allow(unused_variables)]
+						Self::#variant_name { #( #args_name ),* } => {
+							crate::support::DispatchInfo { weight: #weight }
 						},
 					)*
 				}
-				Ok(())
+			}
+		}
+	};
+
+	// How each function is called when dispatching: on the pallet, or on the whole runtime for
+	// functions taking `runtime: &mut T`.
+	let receiver = methods
+		.iter()
+		.map(|method| match method.with_runtime {
+			true => quote!(Self::),
+			false => quote!(<T as crate::support::GetPallet<Self>>::pallet(runtime).),
+		})
+		.collect::<Vec<_>>();
+	let runtime_arg = methods
+		.iter()
+		.map(|method| method.with_runtime.then(|| quote!(runtime,)))
+		.collect::<Vec<_>>();
+
+	// If none of the functions need the whole runtime, the pallet can dispatch its calls by itself,
+	// and the runtime simply forwards them to the pallet.
+	let pallet_dispatch_impl = if methods.iter().all(|method| !method.with_runtime) {
+		quote! {
+			// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
+			// appropriate function call with all arguments, including the `origin`.
+			//
+			// Functions can return either a `DispatchResult`, using all of their declared weight, or a
+			// `DispatchResultWithPostInfo` with the weight they actually used.
+			//
+			// The call is executed inside a storage transaction, so the pallet must implement
+			// `support::Transactional`.
+			impl<T: #config_trait> crate::support::Dispatch for #pallet_struct<T> {
+				type Origin = T::RuntimeOrigin;
+				type Call = Call<T>;
+
+				fn dispatch(
+					&mut self,
+					origin: Self::Origin,
+					call: Self::Call,
+				) -> crate::support::DispatchResultWithPostInfo {
+					crate::support::Transactional::with_transaction(self, |pallet| {
+						match call {
+							#(
+								Call::#variant_name { #( #args_name ),* } => {
+									pallet.#fn_name(
+										// Note that we assume the first argument of every call is the `origin`.
+										origin,
+										#( #args_name ),*
+									)
+									.map(Into::into)
+									.map_err(Into::into)
+								},
+							)*
+						}
+					})
+				}
+			}
+
+			// Dispatch logic at the runtime level, simply forwarding each call to the pallet.
+			impl<T: #config_trait> crate::support::DispatchIn<T> for #pallet_struct<T>
+			where
+				T: crate::support::GetPallet<Self>,
+			{
+				type Origin = T::RuntimeOrigin;
+				type Call = Call<T>;
+
+				fn dispatch_in(
+					runtime: &mut T,
+					origin: Self::Origin,
+					call: Self::Call,
+				) -> crate::support::DispatchResultWithPostInfo {
+					let pallet = <T as crate::support::GetPallet<Self>>::pallet(runtime);
+					crate::support::Dispatch::dispatch(pallet, origin, call)
+				}
+			}
+		}
+	} else {
+		quote! {
+			// Dispatch logic at the runtime level, for pallets with functions which need the whole
+			// runtime. Functions taking `self` are called on the pallet, taken from the runtime with
+			// `support::GetPallet`, and functions taking `runtime: &mut T` are given the runtime.
+			//
+			// The call is executed inside a storage transaction spanning the whole runtime.
+			impl<T: #config_trait> crate::support::DispatchIn<T> for #pallet_struct<T>
+			where
+				T: crate::support::GetPallet<Self> + crate::support::Transactional,
+			{
+				type Origin = T::RuntimeOrigin;
+				type Call = Call<T>;
+
+				fn dispatch_in(
+					runtime: &mut T,
+					origin: Self::Origin,
+					call: Self::Call,
+				) -> crate::support::DispatchResultWithPostInfo {
+					crate::support::Transactional::with_transaction(runtime, |runtime| {
+						match call {
+							#(
+								Call::#variant_name { #( #args_name ),* } => {
+									#receiver #fn_name(
+										#runtime_arg
+										origin,
+										#( #args_name ),*
+									)
+									.map(Into::into)
+									.map_err(Into::into)
+								},
+							)*
+						}
+					})
+				}
 			}
 		}
 	};
 
 	// Return the generated code.
-	dispatch_impl.into()
+	quote! {
+		#dispatch_impl
+		#pallet_dispatch_impl
+	}
 }
@@ -6,20 +6,29 @@ pub fn call(
 	_attr: proc_macro::TokenStream,
 	item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-	// The final expanded code will be placed here.
-	// Since our macro only adds new code, our final product will contain all of our old code too,
-	// hence we clone `item`.
-	let mut finished = item.clone();
-	let item_mod = syn::parse_macro_input!(item as syn::Item);
+	let mut item_mod = syn::parse_macro_input!(item as syn::Item);
 
 	// First we parse the call functions implemented for the pallet...
-	let generated: proc_macro::TokenStream = match parse::CallDef::try_from(item_mod.clone()) {
+	let generated = match parse::CallDef::try_from(item_mod.clone()) {
 		// ..then we generate our new code.
-		Ok(def) => expand::expand_call(def).into(),
-		Err(e) => e.to_compile_error().into(),
+		Ok(def) => expand::expand_call(def),
+		Err(e) => return e.to_compile_error().into(),
 	};
 
+	// Our macro only adds new code, so our final product contains all of our old code too, except
+	// for the `#[weight(...)]` attributes, which are not valid Rust on their own.
+	if let syn::Item::Impl(item_impl) = &mut item_mod {
+		for item in &mut item_impl.items {
+			if let syn::ImplItem::Fn(method) = item {
+				method.attrs.retain(|attr| !attr.path().is_ident("weight"));
+			}
+		}
+	}
+
 	// Add our generated code to the end, and return the final result.
-	finished.extend(generated);
-	return finished;
+	quote::quote! {
+		#item_mod
+		#generated
+	}
+	.into()
 }
@@ -4,7 +4,7 @@ use syn::spanned::Spanned;
 // Custom keywords we match to when parsing the calls in a pallet.
 mod keyword {
 	syn::custom_keyword!(T);
-	syn::custom_keyword!(AccountId);
+	syn::custom_keyword!(RuntimeOrigin);
 }
 
 /// This object will collect all the information we need to keep while parsing the callable
@@ -14,6 +14,9 @@ pub struct CallDef {
 	/// This is the name of the pallet struct where the callable functions are implemented. We
 	/// mostly assume it is `Pallet`.
 	pub pallet_struct: syn::Ident,
+	/// This is the name of the config trait of the pallet, taken from the `impl<T: Config>`
+	/// generics. We mostly assume it is `Config`.
+	pub config_trait: syn::Path,
 	/// This is a list of the callable functions exposed by this pallet. See `CallVariantDef`.
 	pub methods: Vec<CallVariantDef>,
 }
@@ -23,8 +26,14 @@ pub struct CallDef {
 pub struct CallVariantDef {
 	/// The function name.
 	pub name: syn::Ident,
+	/// Whether the function takes `runtime: &mut T` rather than `self`, for calls which need access
+	/// to the whole runtime.
+	pub with_runtime: bool,
 	/// Information on args of the function: `(name, type)`.
 	pub args: Vec<(syn::Ident, Box<syn::Type>)>,
+	/// The weight of the function, given with `#[weight(...)]`. The expression can use the args of
+	/// the function by reference.
+	pub weight: syn::Expr,
 }
 
 impl CallDef {
@@ -42,6 +51,7 @@ impl CallDef {
 			syn::Type::Path(tp) => tp.path.segments.first().unwrap().ident.clone(),
 			_ => panic!("not supported tokens"),
 		};
+		let config_trait = crate::utils::config_trait(&item_impl.generics)?;
 
 		// Here is where we will store all the callable functions.
 		let mut methods = vec![];
@@ -50,31 +60,45 @@ impl CallDef {
 				// Here is where we will store all the args for each callable functions.
 				let mut args = vec![];
 
-				// First argument should be some variant of `self`.
-				match method.sig.inputs.first() {
-					Some(syn::FnArg::Receiver(_)) => {},
-					_ => {
-						let msg = "Invalid call, first argument must be a variant of self";
+				// First argument should be some variant of `self`, or `runtime: &mut T`.
+				let with_runtime = match method.sig.inputs.first() {
+					Some(syn::FnArg::Receiver(_)) => false,
+					Some(syn::FnArg::Typed(arg)) => {
+						check_runtime_arg(arg)?;
+						true
+					},
+					None => {
+						let msg = "Invalid call, first argument must be a variant of self, or \
+							`runtime: &mut T`";
 						return Err(syn::Error::new(method.sig.span(), msg))
 					},
-				}
+				};
 
-				// The second argument should be the `caller: T::AccountId` argument.
-				match method.sig.inputs.iter().skip(1).next() {
+				// The second argument should be the `origin: T::RuntimeOrigin` argument.
+				match method.sig.inputs.iter().nth(1) {
 					Some(syn::FnArg::Typed(arg)) => {
 						// Here we specifically check that this argument is as we expect for
-						// `caller: T::AccountId`.
-						check_caller_arg(arg)?;
+						// `origin: T::RuntimeOrigin`.
+						check_origin_arg(arg)?;
 					},
 					_ => {
-						let msg = "Invalid call, second argument should be `caller: T::AccountId`";
+						let msg = "Invalid call, second argument should be `origin: T::RuntimeOrigin`";
 						return Err(syn::Error::new(method.sig.span(), msg))
 					},
 				}
 
 				let fn_name = method.sig.ident.clone();
 
-				// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
+				// Every callable function must declare its weight.
+				let weight = match method.attrs.iter().find(|attr| attr.path().is_ident("weight")) {
+					Some(attr) => attr.parse_args::<syn::Expr>()?,
+					None => {
+						let msg = "Invalid call, expected a `#[weight(...)]` attribute";
+						return Err(syn::Error::new(method.sig.span(), msg))
+					},
+				};
+
+				// Parsing the rest of the args. Skipping 2 for `self` or `runtime`, and `origin`.
 				for arg in method.sig.inputs.iter().skip(2) {
 					// All arguments should be typed.
 					let arg = if let syn::FnArg::Typed(arg) = arg {
@@ -96,42 +120,77 @@ impl CallDef {
 				}
 
 				// Store all the function name and the arg data for the function.
-				methods.push(CallVariantDef { name: fn_name, args });
+				methods.push(CallVariantDef { name: fn_name, with_runtime, args, weight });
 			}
 		}
 
 		// Return all callable functions for this pallet.
-		Ok(Self { pallet_struct, methods })
+		Ok(Self { pallet_struct, config_trait, methods })
+	}
+}
+
+/// Check runtime arg is exactly: `runtime: &mut T`.
+///
+/// This is kept strict to keep the code simple.
+pub fn check_runtime_arg(arg: &syn::PatType) -> syn::Result<()> {
+	pub struct CheckRuntimeArg;
+	impl syn::parse::Parse for CheckRuntimeArg {
+		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+			input.parse::<syn::Token![&]>()?;
+			input.parse::<syn::Token![mut]>()?;
+			input.parse::<keyword::T>()?;
+			Ok(Self)
+		}
 	}
+
+	// This checks the arg name is `runtime`.
+	match &*arg.pat {
+		syn::Pat::Ident(ident) if ident.ident == "runtime" => {},
+		pat => {
+			let msg = "Invalid name for first parameter: expected `self` or `runtime: &mut T`";
+			return Err(syn::Error::new(pat.span(), msg))
+		},
+	}
+
+	// This checks the type is `&mut T` with `CheckRuntimeArg`.
+	let ty = &arg.ty;
+	syn::parse2::<CheckRuntimeArg>(ty.to_token_stream()).map_err(|e| {
+		let msg = "Invalid type for first parameter: expected `runtime: &mut T`";
+		let mut err = syn::Error::new(ty.span(), msg);
+		err.combine(e);
+		err
+	})?;
+
+	Ok(())
 }
 
-/// Check caller arg is exactly: `caller: T::AccountId`.
+/// Check origin arg is exactly: `origin: T::RuntimeOrigin`.
 ///
 /// This is kept strict to keep the code simple.
-pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
+pub fn check_origin_arg(arg: &syn::PatType) -> syn::Result<()> {
 	pub struct CheckDispatchableFirstArg;
 	impl syn::parse::Parse for CheckDispatchableFirstArg {
 		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
 			input.parse::<keyword::T>()?;
 			input.parse::<syn::Token![::]>()?;
-			input.parse::<keyword::AccountId>()?;
+			input.parse::<keyword::RuntimeOrigin>()?;
 			Ok(Self)
 		}
 	}
 
-	// This checks the arg name is `caller` or `_caller`.
+	// This checks the arg name is `origin` or `_origin`.
 	if let syn::Pat::Ident(ident) = &*arg.pat {
-		// We also support the name as `_caller` for when the variable is unused.
-		if &ident.ident != "caller" && &ident.ident != "_caller" {
-			let msg = "Invalid name for second parameter: expected `caller: T::AccountId`";
+		// We also support the name as `_origin` for when the variable is unused.
+		if &ident.ident != "origin" && &ident.ident != "_origin" {
+			let msg = "Invalid name for second parameter: expected `origin: T::RuntimeOrigin`";
 			return Err(syn::Error::new(ident.span(), msg))
 		}
 	}
 
-	// This checks the type is `T::AccountId` with `CheckDispatchableFirstArg`
+	// This checks the type is `T::RuntimeOrigin` with `CheckDispatchableFirstArg`
 	let ty = &arg.ty;
 	syn::parse2::<CheckDispatchableFirstArg>(ty.to_token_stream()).map_err(|e| {
-		let msg = "Invalid type for second parameter: expected `caller: T::AccountId`";
+		let msg = "Invalid type for second parameter: expected `origin: T::RuntimeOrigin`";
 		let mut err = syn::Error::new(ty.span(), msg);
 		err.combine(e);
 		err
@@ -0,0 +1,128 @@
+use super::parse::{CodecData, CodecDef};
+use quote::{format_ident, quote};
+
+/// The names we bind each of `fields` to when matching on them.
+fn field_bindings(fields: &syn::Fields) -> Vec<syn::Ident> {
+	fields
+		.iter()
+		.enumerate()
+		.map(|(i, field)| field.ident.clone().unwrap_or_else(|| format_ident!("field_{}", i)))
+		.collect()
+}
+
+/// A pattern for `path` binding each of its `fields` to the names from `field_bindings`.
+fn fields_binding(
+	path: proc_macro2::TokenStream,
+	fields: &syn::Fields,
+) -> proc_macro2::TokenStream {
+	let bindings = field_bindings(fields);
+	match fields {
+		syn::Fields::Named(_) => quote! { #path { #( #bindings ),* } },
+		syn::Fields::Unnamed(_) => quote! { #path ( #( #bindings ),* ) },
+		syn::Fields::Unit => quote! { #path },
+	}
+}
+
+/// A constructor for `path` with `fields`, using `values` for each field.
+fn fields_constructor(
+	path: proc_macro2::TokenStream,
+	fields: &syn::Fields,
+	values: &[proc_macro2::TokenStream],
+) -> proc_macro2::TokenStream {
+	match fields {
+		syn::Fields::Named(_) => {
+			let names = fields.iter().map(|field| &field.ident);
+			quote! { #path { #( #names: #values ),* } }
+		},
+		syn::Fields::Unnamed(_) => quote! { #path ( #( #values ),* ) },
+		syn::Fields::Unit => quote! { #path },
+	}
+}
+
+/// See the `fn encode` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_encode(def: CodecDef) -> proc_macro2::TokenStream {
+	let generics = def.bounded_generics(syn::parse_quote!(crate::codec::Encode));
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	let ident = &def.ident;
+
+	// Structs are encoded as their fields, enums as the variant index followed by its fields.
+	let body = match &def.data {
+		CodecData::Struct(fields) => {
+			let bindings = field_bindings(fields);
+			let pattern = fields_binding(quote! { Self }, fields);
+			quote! {
+				let #pattern = self;
+				#( crate::codec::Encode::encode_to(#bindings, dest); )*
+			}
+		},
+		CodecData::Enum(variants) => {
+			let arms = variants.iter().map(|(index, name, fields)| {
+				let bindings = field_bindings(fields);
+				let pattern = fields_binding(quote! { Self::#name }, fields);
+				quote! {
+					#pattern => {
+						dest.push(#index);
+						#( crate::codec::Encode::encode_to(#bindings, dest); )*
+					},
+				}
+			});
+			quote! {
+				match self {
+					#( #arms )*
+				}
+			}
+		},
+	};
+
+	quote! {
+		impl #impl_generics crate::codec::Encode for #ident #ty_generics #where_clause {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				#body
+			}
+		}
+	}
+}
+
+/// See the `fn decode` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_decode(def: CodecDef) -> proc_macro2::TokenStream {
+	let generics = def.bounded_generics(syn::parse_quote!(crate::codec::Decode));
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	let ident = &def.ident;
+
+	// Each field is decoded in order.
+	let decode_fields = |fields: &syn::Fields| {
+		fields
+			.iter()
+			.map(|_| quote! { crate::codec::Decode::decode(input)? })
+			.collect::<Vec<_>>()
+	};
+
+	let body = match &def.data {
+		CodecData::Struct(fields) => {
+			let constructor = fields_constructor(quote! { Self }, fields, &decode_fields(fields));
+			quote! { Ok(#constructor) }
+		},
+		CodecData::Enum(variants) => {
+			let arms = variants.iter().map(|(index, name, fields)| {
+				let constructor =
+					fields_constructor(quote! { Self::#name }, fields, &decode_fields(fields));
+				quote! { #index => Ok(#constructor), }
+			});
+			let msg = format!("invalid variant index for `{ident}`");
+			quote! {
+				match <u8 as crate::codec::Decode>::decode(input)? {
+					#( #arms )*
+					_ => Err(crate::codec::Error::Invalid(#msg)),
+				}
+			}
+		},
+	};
+
+	quote! {
+		impl #impl_generics crate::codec::Decode for #ident #ty_generics #where_clause {
+			fn decode(input: &mut &[u8]) -> Result<Self, crate::codec::Error> {
+				#body
+			}
+		}
+	}
+}
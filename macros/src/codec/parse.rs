@@ -0,0 +1,119 @@
+use quote::ToTokens;
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing a type deriving
+/// `Encode` or `Decode`.
+#[derive(Debug)]
+pub struct CodecDef {
+	/// The name of the type.
+	pub ident: syn::Ident,
+	/// The generics of the type, including the `where` clause for the codec traits.
+	pub generics: syn::Generics,
+	/// The layout of the type.
+	pub data: CodecData,
+}
+
+/// The layout of a type deriving `Encode` or `Decode`.
+#[derive(Debug)]
+pub enum CodecData {
+	Struct(syn::Fields),
+	/// The variants of the enum, each with the index it is encoded with.
+	Enum(Vec<(u8, syn::Ident, syn::Fields)>),
+}
+
+impl CodecDef {
+	pub fn try_from(item: syn::DeriveInput) -> syn::Result<Self> {
+		let data = match item.data {
+			syn::Data::Struct(data) => CodecData::Struct(data.fields),
+			syn::Data::Enum(data) => {
+				let mut variants = vec![];
+				for (i, variant) in data.variants.into_iter().enumerate() {
+					// Variants are encoded by their position, unless `#[codec(index = N)]` is given.
+					let index = match codec_index(&variant.attrs)? {
+						Some(index) => index,
+						None => u8::try_from(i).map_err(|_| {
+							syn::Error::new(variant.span(), "Invalid codec, too many variants")
+						})?,
+					};
+					if variants.iter().any(|(other, _, _)| *other == index) {
+						let msg = "Invalid codec, duplicate variant index";
+						return Err(syn::Error::new(variant.span(), msg));
+					}
+					variants.push((index, variant.ident, variant.fields));
+				}
+				CodecData::Enum(variants)
+			},
+			syn::Data::Union(_) => {
+				return Err(syn::Error::new(
+					item.ident.span(),
+					"Invalid codec, unions are not supported",
+				));
+			},
+		};
+
+		Ok(Self { ident: item.ident, generics: item.generics, data })
+	}
+
+	/// All the field types of the type.
+	pub fn field_types(&self) -> Vec<&syn::Type> {
+		match &self.data {
+			CodecData::Struct(fields) => fields.iter().map(|field| &field.ty).collect(),
+			CodecData::Enum(variants) => variants
+				.iter()
+				.flat_map(|(_, _, fields)| fields.iter().map(|field| &field.ty))
+				.collect(),
+		}
+	}
+
+	/// The generics of the type, with `bound` added to every field type using a generic parameter.
+	///
+	/// Bounding the field types rather than the parameters themselves means that e.g. a `Call<T>`
+	/// with a `T::AccountId` field only requires `T::AccountId` to be encodable, not `T`.
+	pub fn bounded_generics(&self, bound: syn::Path) -> syn::Generics {
+		let params = self
+			.generics
+			.type_params()
+			.map(|param| param.ident.to_string())
+			.collect::<Vec<_>>();
+		let mut generics = self.generics.clone();
+		let where_clause = generics.make_where_clause();
+		for ty in self.field_types() {
+			if uses_params(ty, &params) {
+				where_clause.predicates.push(syn::parse_quote! { #ty: #bound });
+			}
+		}
+		generics
+	}
+}
+
+/// Check whether `ty` mentions any of the generic `params`.
+fn uses_params(ty: &syn::Type, params: &[String]) -> bool {
+	ty.to_token_stream().into_iter().any(|token| uses_params_token(&token, params))
+}
+
+fn uses_params_token(token: &proc_macro2::TokenTree, params: &[String]) -> bool {
+	match token {
+		proc_macro2::TokenTree::Ident(ident) => params.contains(&ident.to_string()),
+		proc_macro2::TokenTree::Group(group) => {
+			group.stream().into_iter().any(|token| uses_params_token(&token, params))
+		},
+		_ => false,
+	}
+}
+
+/// Parse the index of a variant from its `#[codec(index = N)]` attribute, if any.
+fn codec_index(attrs: &[syn::Attribute]) -> syn::Result<Option<u8>> {
+	let mut index = None;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("codec")) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("index") {
+				let lit: syn::LitInt = meta.value()?.parse()?;
+				index = Some(lit.base10_parse::<u8>()?);
+				Ok(())
+			} else {
+				Err(meta.error("Invalid codec attribute, expected `index = N`"))
+			}
+		})?;
+	}
+	Ok(index)
+}
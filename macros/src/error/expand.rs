@@ -0,0 +1,67 @@
+use super::parse::ErrorDef;
+use quote::quote;
+
+/// See the `fn error` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_error(def: ErrorDef) -> proc_macro2::TokenStream {
+	let ErrorDef { mut item_enum, pallet_struct, config_trait, variants } = def;
+	let error = item_enum.ident.clone();
+
+	// The generic `T` is not used by any of the user's variants, so we add a hidden variant which
+	// uses it. It can never be constructed, since `Infallible` has no values.
+	item_enum.variants.push(syn::parse_quote! {
+		#[doc(hidden)]
+		__Ignore(core::marker::PhantomData<T>, core::convert::Infallible)
+	});
+
+	// The index of each variant, which identifies it inside of a `DispatchError`.
+	let index = (0..variants.len()).map(|i| i as u8).collect::<Vec<_>>();
+	// The name of each variant as a string, for debugging.
+	let name = variants.iter().map(|variant| variant.to_string()).collect::<Vec<_>>();
+
+	quote! {
+		#item_enum
+
+		impl<T: #config_trait> #error<T> {
+			/// The index of this error in the pallet's `Error` enum.
+			pub fn index(&self) -> u8 {
+				match self {
+					#( Self::#variants => #index, )*
+					Self::__Ignore(_, never) => match *never {},
+				}
+			}
+
+			/// The name of this error.
+			pub fn as_str(&self) -> &'static str {
+				match self {
+					#( Self::#variants => #name, )*
+					Self::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		impl<T: #config_trait> core::fmt::Debug for #error<T> {
+			fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+				f.write_str(self.as_str())
+			}
+		}
+
+		impl<T: #config_trait> PartialEq for #error<T> {
+			fn eq(&self, other: &Self) -> bool {
+				self.index() == other.index()
+			}
+		}
+
+		impl<T: #config_trait> Eq for #error<T> {}
+
+		// Errors are converted into a `DispatchError::Module`, using the index of the pallet in
+		// the runtime as given by `PalletInfo`.
+		impl<T: #config_trait> From<#error<T>> for crate::support::DispatchError {
+			fn from(error: #error<T>) -> Self {
+				let pallet_index =
+					<T::PalletInfo as crate::support::PalletInfo>::index::<#pallet_struct<T>>()
+						.expect("pallet is part of the runtime");
+				Self::Module { pallet_index: pallet_index as u8, error_index: error.index() }
+			}
+		}
+	}
+}
@@ -0,0 +1,57 @@
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the `Error` enum of
+/// a pallet.
+#[derive(Debug)]
+pub struct ErrorDef {
+	/// The `Error` enum as written by the user.
+	pub item_enum: syn::ItemEnum,
+	/// This is the name of the pallet struct the errors belong to. We mostly assume it is `Pallet`,
+	/// but it can be given as an argument of the attribute, e.g. `#[macros::error(MyPallet)]`.
+	pub pallet_struct: syn::Ident,
+	/// This is the name of the config trait bounding the generic parameter of the enum.
+	pub config_trait: syn::Path,
+	/// The name of each error variant, in declaration order.
+	pub variants: Vec<syn::Ident>,
+}
+
+impl ErrorDef {
+	pub fn try_from(attr: proc_macro2::TokenStream, item: syn::Item) -> syn::Result<Self> {
+		// First we check that we are parsing an `enum`.
+		let item_enum = if let syn::Item::Enum(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid pallet::error, expected item enum"))
+		};
+
+		// The pallet struct defaults to `Pallet` when no argument is given.
+		let pallet_struct = if attr.is_empty() {
+			syn::Ident::new("Pallet", proc_macro2::Span::call_site())
+		} else {
+			syn::parse2::<syn::Ident>(attr)?
+		};
+
+		let config_trait = crate::utils::config_trait(&item_enum.generics)?;
+
+		// Errors are only identified by their index, so they can not carry any data.
+		let mut variants = vec![];
+		for variant in &item_enum.variants {
+			if !matches!(variant.fields, syn::Fields::Unit) {
+				let msg = "Invalid pallet::error, variants can not have fields";
+				return Err(syn::Error::new(variant.span(), msg))
+			}
+			if variant.discriminant.is_some() {
+				let msg = "Invalid pallet::error, variants can not have explicit discriminants";
+				return Err(syn::Error::new(variant.span(), msg))
+			}
+			variants.push(variant.ident.clone());
+		}
+
+		if variants.len() > u8::MAX as usize {
+			let msg = "Invalid pallet::error, too many variants";
+			return Err(syn::Error::new(item_enum.span(), msg))
+		}
+
+		Ok(Self { item_enum, pallet_struct, config_trait, variants })
+	}
+}
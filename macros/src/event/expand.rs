@@ -0,0 +1,122 @@
+use super::parse::EventDef;
+use quote::quote;
+
+/// See the `fn event` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_event(def: EventDef) -> proc_macro2::TokenStream {
+	let EventDef { mut item_enum, pallet_struct, config_trait, variants } = def;
+	let event = item_enum.ident.clone();
+
+	// The generic `T` may not be used by any of the user's variants, e.g. for events which carry no
+	// accounts, so we add a hidden variant which uses it. It can never be constructed, since
+	// `Infallible` has no values.
+	item_enum.variants.push(syn::parse_quote! {
+		#[doc(hidden)]
+		__Ignore(core::marker::PhantomData<T>, core::convert::Infallible)
+	});
+
+	// This is a vector of all the variant names.
+	let variant_name = variants.iter().map(|(name, _)| name).collect::<Vec<_>>();
+	// This is a nested vector of the field names of each variant.
+	let fields_name = variants
+		.iter()
+		.map(|(_, fields)| fields.iter().map(|(name, _)| name).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+	// The same field names, which we use to bind the fields of a second event when comparing.
+	let fields_other = variants
+		.iter()
+		.map(|(_, fields)| {
+			fields
+				.iter()
+				.map(|(name, _)| quote::format_ident!("other_{}", name))
+				.collect::<Vec<_>>()
+		})
+		.collect::<Vec<_>>();
+	// This is a list of all the field types, used to bound the trait implementations. We can not
+	// simply `#[derive]` them, since that would require `T` itself to implement those traits.
+	let field_type = variants
+		.iter()
+		.flat_map(|(_, fields)| fields.iter().map(|(_, ty)| ty))
+		.collect::<Vec<_>>();
+
+	quote! {
+		#item_enum
+
+		impl<T: #config_trait> Clone for #event<T> where #( #field_type: Clone, )* {
+			fn clone(&self) -> Self {
+				match self {
+					#(
+						Self::#variant_name { #( #fields_name ),* } => Self::#variant_name {
+							#( #fields_name: #fields_name.clone() ),*
+						},
+					)*
+					Self::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		impl<T: #config_trait> PartialEq for #event<T> where #( #field_type: PartialEq, )* {
+			fn eq(&self, other: &Self) -> bool {
+				match (self, other) {
+					#(
+						(
+							Self::#variant_name { #( #fields_name ),* },
+							Self::#variant_name { #( #fields_name: #fields_other ),* },
+						) => true #( && #fields_name == #fields_other )*,
+					)*
+					#[allow(unreachable_patterns)]
+					_ => false,
+				}
+			}
+		}
+
+		impl<T: #config_trait> core::fmt::Debug for #event<T>
+		where
+			#( #field_type: core::fmt::Debug, )*
+		{
+			fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+				match self {
+					#(
+						Self::#variant_name { #( #fields_name ),* } => f
+							.debug_struct(stringify!(#variant_name))
+							#( .field(stringify!(#fields_name), #fields_name) )*
+							.finish(),
+					)*
+					Self::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		// Events are buffered in the `events` field of the pallet, until the runtime collects them
+		// at the end of each extrinsic.
+		impl<T: #config_trait> #pallet_struct<T> {
+			/// Deposit an event, to be collected by the runtime once the extrinsic is done.
+			fn deposit_event(&mut self, event: #event<T>) {
+				self.events.push(event);
+			}
+
+			/// Take all the events deposited since the last time this was called.
+			pub fn take_events(&mut self) -> Vec<#event<T>> {
+				self.events.take()
+			}
+		}
+
+		// A pallet keeps its state in its `storage` field, so a transactional layer covers both the
+		// storage and the events buffered alongside it, which are dropped if the call fails.
+		impl<T: #config_trait> crate::support::Transactional for #pallet_struct<T> {
+			fn start_transaction(&mut self) {
+				crate::support::Transactional::start_transaction(&mut self.storage);
+				crate::support::Transactional::start_transaction(&mut self.events);
+			}
+
+			fn commit_transaction(&mut self) {
+				crate::support::Transactional::commit_transaction(&mut self.storage);
+				crate::support::Transactional::commit_transaction(&mut self.events);
+			}
+
+			fn rollback_transaction(&mut self) {
+				crate::support::Transactional::rollback_transaction(&mut self.storage);
+				crate::support::Transactional::rollback_transaction(&mut self.events);
+			}
+		}
+	}
+}
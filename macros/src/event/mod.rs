@@ -0,0 +1,17 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn event` docs at the `lib.rs` of this crate for a high level definition.
+pub fn event(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let item_enum = syn::parse_macro_input!(item as syn::Item);
+
+	// Like the `Error` enum, the `Event` enum itself is modified, so the original code is not kept
+	// as-is. Instead, the expansion re-emits the enum with an extra hidden variant.
+	match parse::EventDef::try_from(attr.into(), item_enum) {
+		Ok(def) => expand::expand_event(def).into(),
+		Err(e) => e.to_compile_error().into(),
+	}
+}
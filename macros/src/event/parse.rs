@@ -0,0 +1,56 @@
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the `Event` enum of
+/// a pallet.
+#[derive(Debug)]
+pub struct EventDef {
+	/// The `Event` enum as written by the user.
+	pub item_enum: syn::ItemEnum,
+	/// This is the name of the pallet struct emitting the events. We mostly assume it is `Pallet`,
+	/// but it can be given as an argument of the attribute, e.g. `#[macros::event(MyPallet)]`.
+	pub pallet_struct: syn::Ident,
+	/// This is the name of the config trait bounding the generic parameter of the enum.
+	pub config_trait: syn::Path,
+	/// The variant names, each with the list of its fields: `(name, type)`.
+	pub variants: Vec<(syn::Ident, Vec<(syn::Ident, syn::Type)>)>,
+}
+
+impl EventDef {
+	pub fn try_from(attr: proc_macro2::TokenStream, item: syn::Item) -> syn::Result<Self> {
+		// First we check that we are parsing an `enum`.
+		let item_enum = if let syn::Item::Enum(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid pallet::event, expected item enum"))
+		};
+
+		// The pallet struct defaults to `Pallet` when no argument is given.
+		let pallet_struct = if attr.is_empty() {
+			syn::Ident::new("Pallet", proc_macro2::Span::call_site())
+		} else {
+			syn::parse2::<syn::Ident>(attr)?
+		};
+
+		let config_trait = crate::utils::config_trait(&item_enum.generics)?;
+
+		// We keep events simple, so every variant is expected to have named fields.
+		let mut variants = vec![];
+		for variant in &item_enum.variants {
+			let fields = match &variant.fields {
+				syn::Fields::Named(fields) => fields
+					.named
+					.iter()
+					.map(|field| (field.ident.clone().expect("fields are named"), field.ty.clone()))
+					.collect(),
+				syn::Fields::Unit => vec![],
+				syn::Fields::Unnamed(_) => {
+					let msg = "Invalid pallet::event, variants must have named fields";
+					return Err(syn::Error::new(variant.span(), msg))
+				},
+			};
+			variants.push((variant.ident.clone(), fields));
+		}
+
+		Ok(Self { item_enum, pallet_struct, config_trait, variants })
+	}
+}
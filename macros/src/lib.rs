@@ -1,6 +1,34 @@
 mod call;
+mod codec;
+mod error;
+mod event;
 mod runtime;
+mod storage;
+mod utils;
 
+/// Expand the callable functions of a pallet, written in an `impl<T: Config> Pallet<T>` block.
+///
+/// The first argument of every function after `self` must be `origin: T::RuntimeOrigin`, which
+/// can be checked with `support::ensure_signed` or `support::ensure_root`.
+/// Functions which need the whole runtime, e.g. to dispatch other calls, take `runtime: &mut T`
+/// instead of `self`.
+///
+/// Every function must declare its weight with `#[weight(...)]`, an expression which can use the
+/// arguments of the function by reference. Functions return either a `support::DispatchResult`,
+/// or a `support::DispatchResultWithPostInfo` carrying the weight they actually used.
+///
+/// This generates:
+/// - `enum Call<T>` - with one variant per function, named in `CamelCase`, holding the arguments of
+///   the function except `self` and `origin`. It derives `codec::Encode` and `codec::Decode`, and
+///   implements `Clone` bounded on the argument types.
+/// - implements the trait `support::GetDispatchInfo` for `Call<T>`, giving the declared weight of
+///   each call.
+/// - implements the trait `support::Dispatch` for the pallet, routing each `Call` variant to its
+///   function along with the origin, inside of a storage transaction. This is left out if any
+///   function takes `runtime: &mut T`.
+/// - implements the trait `support::DispatchIn<T>` for the pallet, dispatching calls from within
+///   the runtime `T`, which the runtime uses to route its calls. It reaches the pallet through
+///   `support::GetPallet`.
 #[proc_macro_attribute]
 pub fn call(
 	attr: proc_macro::TokenStream,
@@ -9,22 +37,111 @@ pub fn call(
 	call::call(attr, item)
 }
 
+/// Expand the `Error<T>` enum of a pallet.
+///
+/// The enum must only have unit variants. This adds a hidden variant using `T`, and generates:
+/// - `fn index()` and `fn as_str()` - identifying each variant by its position and name.
+/// - implementations of `Debug`, `PartialEq` and `Eq`.
+/// - `From<Error<T>> for support::DispatchError` - converting errors into
+///   `DispatchError::Module`, using the index of the pallet in the runtime given by `PalletInfo`.
+///
+/// The pallet struct is assumed to be `Pallet`, another one can be given as an argument, e.g.
+/// `#[macros::error(BalancesPallet)]`.
+#[proc_macro_attribute]
+pub fn error(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	error::error(attr, item)
+}
+
+/// Expand the `Event<T>` enum of a pallet.
+///
+/// Every variant must have named fields, or no fields at all. This adds a hidden variant using `T`,
+/// so events need not use `T` themselves, and generates:
+/// - implementations of `Clone`, `PartialEq` and `Debug`, bounded on the types of the fields rather
+///   than on `T`.
+/// - `fn deposit_event()` and `fn take_events()` on the pallet struct, which buffer events in its
+///   `events: TransactionalVec<Event<T>>` field until the runtime collects them.
+/// - implements the trait `support::Transactional` for the pallet struct, opening a transactional
+///   layer on both its `storage: storage::Storage` field and its `events` field.
+///
+/// The pallet struct is assumed to be `Pallet`, another one can be given as an argument, e.g.
+/// `#[macros::event(BalancesPallet)]`.
+#[proc_macro_attribute]
+pub fn event(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	event::event(attr, item)
+}
+
+/// Expand the storage items of a pallet, declared as type aliases in an `impl<T: Config> Pallet<T>`
+/// block, e.g. `type Balances = StorageMap<T::AccountId, T::Balance>;`.
+///
+/// Each item must be a `storage::StorageValue`, `storage::StorageMap` or
+/// `storage::StorageDoubleMap`. This replaces the block with one generating an accessor function
+/// per item, named after the item in `snake_case` and keeping its docs and visibility. The accessor
+/// returns the item on the `storage` field of the pallet, with keys prefixed by the hashed names of
+/// the pallet, as given by `support::PalletInfo`, and of the item.
+#[proc_macro_attribute]
+pub fn storage(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	storage::storage(attr, item)
+}
+
 /// Expand the `Runtime` definition.
 ///
 /// This generates function implementations on `Runtime`:
-/// - `fn new()` - which generates a new instance of the runtime, by instantiating all the pallets
-///   included in the runtime.
+/// - `fn from_genesis()` - which generates a new instance of the runtime, by instantiating all the
+///   pallets included in the runtime with a handle to the same `storage::Storage`, and setting up
+///   their genesis state with `fn build_genesis()`. It then records the genesis block, which
+///   commits to the genesis state. It fails with the error of the first pallet which rejects its
+///   genesis configuration.
+/// - `fn new()` - which does the same with the default genesis configuration.
 /// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
 ///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+///   valid block number, and that every extrinsic is signed by its caller at most
+///   `SystemConfig::MORTALITY_PERIOD` blocks before, and carries their next nonce. Every extrinsic
+///   is charged a fee through `support::ChargeTransaction` before it is dispatched, and refunded
+///   for the weight it did not use. The weight of an extrinsic, added to the weight actually used
+///   by the ones before it, can not exceed `SystemConfig::MAXIMUM_BLOCK_WEIGHT`. It checks the
+///   parent hash and extrinsics root of the header before executing the block, and its state root
+///   after, rejecting the whole block inside a storage transaction if any of them do not match. It
+///   also clears the events of the previous block, and collects the events of every pallet into the
+///   system pallet after each extrinsic, followed by a `system::Event::ExtrinsicSuccess` or
+///   `system::Event::ExtrinsicFailed`. The `support::Hooks` of every pallet are called in
+///   declaration order around the extrinsics: `on_initialize` before them, counting towards the
+///   weight of the block, then `on_idle` with the weight left in the block, and `on_finalize`. The
+///   events emitted by the hooks are recorded in the `Phase::Initialization` and
+///   `Phase::Finalization` phases.
+/// - `fn build_block()` - which executes a list of extrinsics as the next block, and returns the
+///   block with a header filled in to match, as a block author would.
+/// - `fn state_root()` - the root of the trie holding the storage shared by all the pallets.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
 /// - Note: For simplicity, we assume that the system pallet is not callable.
 /// - `enum RuntimeCall` - an "outer"-enum representing the accumulation of all possible calls to
-///   all pallets. The system pallet is not included.
-/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
-///   logic like incrementing the nonce of the user is included in the generated code. The system
-///   pallet is not included.
+///   all pallets. The system pallet is not included. It derives `Clone` and `codec::Encode`, using
+///   the index of each pallet, and implements `codec::Decode` the same way, rejecting calls nested
+///   more than `support::MAX_EXTRINSIC_DEPTH` levels deep. It also implements
+///   `support::GetDispatchInfo`.
+/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet, with a
+///   `types::RuntimeOrigin`, through its `support::DispatchIn` implementation. Extrinsics are
+///   dispatched with the signed origin of their caller. The system pallet is not included.
+/// - implements the trait `support::GetPallet` for every pallet, including system.
+/// - `enum RuntimeEvent` - an "outer"-enum representing the accumulation of all the events emitted
+///   by all pallets, with a `System` variant for the outcome of every extrinsic.
+/// - `struct GenesisConfig` - an accumulation of the `GenesisConfig<Runtime>` of each pallet,
+///   including system. It derives `Default` and `serde::Deserialize`, naming pallets in
+///   `camelCase`.
+/// - implements the trait `support::PalletInfo`, indexing pallets in declaration order starting
+///   with system at index 0, so pallet errors can report which pallet they come from. Pallets are
+///   named after their field in `CamelCase`, which prefixes the keys of their storage items.
+/// - implements the trait `support::Transactional`, so every dispatched call runs inside a storage
+///   transaction spanning all pallets, including system.
 #[proc_macro_attribute]
 pub fn runtime(
 	attr: proc_macro::TokenStream,
@@ -32,3 +149,25 @@ pub fn runtime(
 ) -> proc_macro::TokenStream {
 	runtime::runtime(attr, item)
 }
+
+/// Derive `codec::Encode` for a struct or an enum.
+///
+/// Structs are encoded as the concatenation of their fields. Enums are encoded as the index of the
+/// variant as a `u8`, followed by its fields. Variants are indexed by their position, unless given
+/// an explicit index with `#[codec(index = N)]`.
+///
+/// Rather than bounding the generic parameters, every field type which uses them must implement
+/// `Encode`. This way a `Call<T>` with a `T::AccountId` field does not require `T: Encode`.
+#[proc_macro_derive(Encode, attributes(codec))]
+pub fn encode(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	codec::encode(item)
+}
+
+/// Derive `codec::Decode` for a struct or an enum.
+///
+/// This decodes the encoding produced by `#[derive(Encode)]`, see its docs for the format. Unknown
+/// variant indices are rejected with `codec::Error::Invalid`.
+#[proc_macro_derive(Decode, attributes(codec))]
+pub fn decode(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	codec::decode(item)
+}
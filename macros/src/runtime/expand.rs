@@ -3,45 +3,353 @@ use quote::quote;
 
 /// See the `fn runtime` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
-	let RuntimeDef { runtime_struct, pallets } = def;
+	let RuntimeDef { runtime_struct, system_type, pallets } = def;
 
 	// This is a vector of all the pallet names, not including system.
 	let pallet_names = pallets.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
 	// This is a vector of all the pallet types, not including system.
 	let pallet_types = pallets.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>();
+	// This is a vector of the `RuntimeCall` and `RuntimeEvent` variant names, i.e. the pallet
+	// names in `CamelCase`.
+	let pallet_variants =
+		pallet_names.iter().map(crate::utils::camel_case).collect::<Vec<_>>();
+
+	// The index of each pallet in the runtime, not including system, which always has index 0.
+	let pallet_indices = (1..=pallets.len()).collect::<Vec<_>>();
+	// The name of each pallet in the runtime, not including system, which is always `System`.
+	let pallet_name_strs =
+		pallet_variants.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+	// The same indices as unsuffixed literals, to be used in `#[codec(index = N)]` attributes.
+	let call_indices = pallet_indices
+		.iter()
+		.map(|index| proc_macro2::Literal::usize_unsuffixed(*index))
+		.collect::<Vec<_>>();
+	// The `Hooks` implementation of each pallet, not including system, called around the
+	// extrinsics of every block.
+	let pallet_hooks = pallet_types
+		.iter()
+		.map(|type_| quote!(<#type_ as crate::support::Hooks<Self, types::BlockNumber>>))
+		.collect::<Vec<_>>();
 
 	// This quote block implements functions on the `Runtime` struct.
 	let runtime_impl = quote! {
 		impl #runtime_struct {
-			// Create a new instance of the main Runtime, by creating a new instance of each pallet.
-			fn new() -> Self {
-				Self {
+			// Create a new instance of the main Runtime, with the default genesis configuration.
+			pub fn new() -> Self {
+				Self::from_genesis(&GenesisConfig::default())
+					.expect("the default genesis configuration is valid")
+			}
+
+			// Create a new instance of the main Runtime, by creating a new instance of each pallet
+			// and setting up its genesis state. All the pallets keep their state in the same
+			// storage. Fails if the genesis configuration of a pallet is invalid.
+			pub fn from_genesis(
+				genesis: &GenesisConfig,
+			) -> Result<Self, crate::support::DispatchError> {
+				let storage = crate::storage::Storage::new();
+				let mut runtime = Self {
 					// Since system is not included in the list of pallets, we manually add it here.
-					system: <system::Pallet::<Self>>::new(),
+					system: <#system_type>::new(storage.clone()),
 					#(
-						#pallet_names: <#pallet_types>::new()
+						#pallet_names: <#pallet_types>::new(storage.clone())
 					),*
+				};
+				runtime.system.build_genesis(&genesis.system)?;
+				#( runtime.#pallet_names.build_genesis(&genesis.#pallet_names)?; )*
+
+				// The genesis block has no extrinsics, and commits to the genesis state of every
+				// pallet. Its hash identifies the chain.
+				let genesis_header = types::Header {
+					parent_hash: Default::default(),
+					block_number: runtime.system.block_number(),
+					state_root: runtime.state_root(),
+					extrinsics_root: crate::support::extrinsics_root::<types::UncheckedExtrinsic>(&[]),
+					digest: Default::default(),
+				};
+				runtime.system.set_block_hash(genesis_header.hash());
+				Ok(runtime)
+			}
+
+			// The root of the current state, committing to the storage of every pallet including
+			// system.
+			pub fn state_root(&self) -> crate::crypto::H256 {
+				self.system.storage.root()
+			}
+
+			// Execute a block of extrinsics, checking it against its header. Increments the block
+			// number.
+			//
+			// The block is executed inside a storage transaction, so a rejected block leaves no
+			// changes behind.
+			pub fn execute_block(&mut self, block: types::Block) -> crate::support::BlockResult {
+				let crate::support::Block { header, extrinsics } = block;
+				// The block must build on the latest block, and commit to its extrinsics.
+				if header.parent_hash != self.system.parent_hash() {
+					return Err(crate::support::BlockError::InvalidParentHash)
+				}
+				if header.extrinsics_root != crate::support::extrinsics_root(&extrinsics) {
+					return Err(crate::support::BlockError::InvalidExtrinsicsRoot)
 				}
+				let events = crate::support::Transactional::with_transaction(self, |runtime| {
+					let events = runtime.apply_extrinsics(header.block_number, extrinsics)?;
+					// The header commits to the state after executing the block.
+					if header.state_root != runtime.state_root() {
+						return Err(crate::support::BlockError::InvalidStateRoot)
+					}
+					Ok(events)
+				})?;
+				self.finalize_block(&header, events);
+				Ok(())
+			}
+
+			// Build the next block out of `extrinsics` and execute it, filling in its header.
+			//
+			// This is how a block author creates blocks, which other nodes can then check with
+			// `execute_block`.
+			pub fn build_block(
+				&mut self,
+				extrinsics: Vec<types::UncheckedExtrinsic>,
+			) -> Result<types::Block, crate::support::BlockError> {
+				let mut header = types::Header {
+					parent_hash: self.system.parent_hash(),
+					block_number: self.system.next_block_number(),
+					state_root: Default::default(),
+					extrinsics_root: crate::support::extrinsics_root(&extrinsics),
+					digest: Default::default(),
+				};
+				let events = crate::support::Transactional::with_transaction(self, |runtime| {
+					let events = runtime.apply_extrinsics(header.block_number, extrinsics.clone())?;
+					header.state_root = runtime.state_root();
+					Ok(events)
+				})?;
+				self.finalize_block(&header, events);
+				Ok(crate::support::Block { header, extrinsics })
 			}
 
-			// Execute a block of extrinsics. Increments the block number.
-			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
+			// Check and dispatch `extrinsics` as the block `block_number`, calling the hooks of
+			// every pallet around them, returning the events emitted along with the part of the
+			// block which emitted them.
+			//
+			// Every extrinsic pays for its weight and length before being dispatched, and is
+			// refunded for the weight it did not use. The weight used by the block, including the
+			// weight used by `on_initialize`, can not exceed `SystemConfig::MAXIMUM_BLOCK_WEIGHT`.
+			// The weight left over is offered to `on_idle`.
+			fn apply_extrinsics(
+				&mut self,
+				block_number: types::BlockNumber,
+				extrinsics: Vec<types::UncheckedExtrinsic>,
+			) -> Result<Vec<(crate::types::Phase, RuntimeEvent)>, crate::support::BlockError> {
+				// The mortality, signatures and nonces are checked before executing anything, so a
+				// block with an invalid extrinsic is rejected as a whole.
+				let extrinsics = extrinsics
+					.into_iter()
+					.enumerate()
+					.map(|(i, extrinsic)| {
+						let invalid = |error| crate::support::BlockError::InvalidTransaction {
+							extrinsic_index: i as u32,
+							error,
+						};
+						let len = crate::codec::Encode::encode(&extrinsic).len() as u32;
+						let birth_hash = self.system.validate_mortality(extrinsic.birth).map_err(invalid)?;
+						let extrinsic = crate::support::Checkable::check(extrinsic, &birth_hash)
+							.map_err(invalid)?;
+						Ok((extrinsic, len))
+					})
+					.collect::<Result<Vec<_>, _>>()?;
+				let nonces = extrinsics.iter().map(|(extrinsic, _)| (&extrinsic.caller, extrinsic.nonce));
+				self.system.validate_nonces(nonces).map_err(|(extrinsic_index, error)| {
+					crate::support::BlockError::InvalidTransaction { extrinsic_index, error }
+				})?;
+
 				self.system.inc_block_number();
-				if block.header.block_number != self.system.block_number() {
-					return Err(&"block number does not match what is expected")
+				if block_number != self.system.block_number() {
+					return Err(crate::support::BlockError::InvalidBlockNumber)
 				}
-				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
+				let mut events = Vec::new();
+				let mut block_weight: crate::support::Weight = 0;
+
+				// Every pallet initializes the block, in declaration order, before the extrinsics
+				// are dispatched.
+				#(
+					block_weight = block_weight
+						.saturating_add(#pallet_hooks::on_initialize(self, block_number));
+				)*
+				#(
+					for event in self.#pallet_names.take_events() {
+						let event = RuntimeEvent::#pallet_variants(event);
+						events.push((crate::types::Phase::Initialization, event));
+					}
+				)*
+
+				for (i, (extrinsic, len)) in extrinsics.into_iter().enumerate() {
+					let crate::support::Extrinsic { caller, call, .. } = extrinsic;
+					let invalid = |error| crate::support::BlockError::InvalidTransaction {
+						extrinsic_index: i as u32,
+						error,
+					};
+					// The declared weight must fit in the block, and the caller must be able to pay
+					// for it. Otherwise the extrinsic can not be included at all.
+					let info = crate::support::GetDispatchInfo::get_dispatch_info(&call);
+					block_weight = block_weight
+						.checked_add(info.weight)
+						.filter(|weight| *weight <= <Self as crate::types::SystemConfig>::MAXIMUM_BLOCK_WEIGHT)
+						.ok_or(invalid(crate::support::InvalidTransaction::ExhaustsResources))?;
+					crate::support::ChargeTransaction::withdraw_fee(self, &caller, &info, len)
+						.map_err(invalid)?;
+
 					self.system.inc_nonce(&caller);
-					let _res = self.dispatch(caller, call).map_err(|e| {
-						eprintln!(
-							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
-							block.header.block_number, i, e
-						)
-					});
+					let origin = crate::support::RawOrigin::Signed(caller.clone());
+					let (post_info, outcome) = match crate::support::Dispatch::dispatch(self, origin, call) {
+						Ok(post_info) => (post_info, system::Event::ExtrinsicSuccess),
+						Err(e) => (e.post_info, system::Event::ExtrinsicFailed { error: e.error }),
+					};
+					block_weight -= info.weight - post_info.calc_actual_weight(&info);
+					crate::support::ChargeTransaction::refund_fee(self, &caller, &info, &post_info);
+					// Collect the events deposited by each pallet during this extrinsic.
+					let phase = crate::types::Phase::ApplyExtrinsic(i as u32);
+					#(
+						for event in self.#pallet_names.take_events() {
+							events.push((phase, RuntimeEvent::#pallet_variants(event)));
+						}
+					)*
+					// The outcome of the extrinsic comes last, so failed extrinsics can be told apart.
+					events.push((phase, RuntimeEvent::System(outcome)));
 				}
-				Ok(())
+
+				// Once the extrinsics have been dispatched, every pallet may use the weight left in
+				// the block, and then finalizes it, in declaration order.
+				let maximum_weight = <Self as crate::types::SystemConfig>::MAXIMUM_BLOCK_WEIGHT;
+				let mut remaining_weight = maximum_weight.saturating_sub(block_weight);
+				#(
+					let used_weight = #pallet_hooks::on_idle(self, block_number, remaining_weight);
+					remaining_weight = remaining_weight.saturating_sub(used_weight);
+				)*
+				#(
+					#pallet_hooks::on_finalize(self, block_number);
+				)*
+				#(
+					for event in self.#pallet_names.take_events() {
+						let event = RuntimeEvent::#pallet_variants(event);
+						events.push((crate::types::Phase::Finalization, event));
+					}
+				)*
+				Ok(events)
+			}
+
+			// Record the hash of an executed block, and the events it emitted. Events are only kept
+			// for the latest block.
+			fn finalize_block(
+				&mut self,
+				header: &types::Header,
+				events: Vec<(crate::types::Phase, RuntimeEvent)>,
+			) {
+				self.system.set_block_hash(header.hash());
+				self.system.reset_events();
+				for (phase, event) in events {
+					self.system.deposit_event(phase, event);
+				}
+			}
+		}
+	};
+
+
+	// This quote block implements `PalletInfo` for the runtime, identifying pallets by their
+	// position in the `Runtime` struct, and naming them after their field in `CamelCase`.
+	let pallet_info_impl = quote! {
+		impl crate::support::PalletInfo for #runtime_struct {
+			fn index<P: 'static>() -> Option<usize> {
+				let pallet = core::any::TypeId::of::<P>();
+				if pallet == core::any::TypeId::of::<#system_type>() {
+					return Some(0)
+				}
+				#(
+					if pallet == core::any::TypeId::of::<#pallet_types>() {
+						return Some(#pallet_indices)
+					}
+				)*
+				None
+			}
+
+			fn name<P: 'static>() -> Option<&'static str> {
+				let pallet = core::any::TypeId::of::<P>();
+				if pallet == core::any::TypeId::of::<#system_type>() {
+					return Some("System")
+				}
+				#(
+					if pallet == core::any::TypeId::of::<#pallet_types>() {
+						return Some(#pallet_name_strs)
+					}
+				)*
+				None
+			}
+		}
+	};
+
+	// This quote block implements `GetPallet` for every pallet of the runtime, including system, so
+	// pallet calls which need the whole runtime can still reach their own pallet.
+	let get_pallet_impl = quote! {
+		impl crate::support::GetPallet<#system_type> for #runtime_struct {
+			fn pallet(&mut self) -> &mut #system_type {
+				&mut self.system
 			}
 		}
+
+		#(
+			impl crate::support::GetPallet<#pallet_types> for #runtime_struct {
+				fn pallet(&mut self) -> &mut #pallet_types {
+					&mut self.#pallet_names
+				}
+			}
+		)*
+	};
+
+	// This quote block implements `Transactional` for the runtime, by forwarding each transactional
+	// layer to every pallet, including system. Since the pallets share their storage, this opens one
+	// storage layer per pallet, which are all committed or rolled back together.
+	let transactional_impl = quote! {
+		impl crate::support::Transactional for #runtime_struct {
+			fn start_transaction(&mut self) {
+				self.system.start_transaction();
+				#( self.#pallet_names.start_transaction(); )*
+			}
+
+			fn commit_transaction(&mut self) {
+				self.system.commit_transaction();
+				#( self.#pallet_names.commit_transaction(); )*
+			}
+
+			fn rollback_transaction(&mut self) {
+				self.system.rollback_transaction();
+				#( self.#pallet_names.rollback_transaction(); )*
+			}
+		}
+	};
+
+	// This quote block implements the `GenesisConfig` struct.
+	let genesis_impl = quote! {
+		// The genesis configuration of the runtime.
+		// Note that it is just an accumulation of the genesis configuration of each pallet.
+		//
+		// It can be deserialized from JSON, with the pallets named in `camelCase`. Any pallet left
+		// out uses its default configuration.
+		#[derive(Default, serde::Deserialize)]
+		#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+		pub struct GenesisConfig {
+			pub system: system::GenesisConfig<#runtime_struct>,
+			#( pub #pallet_names: #pallet_names::GenesisConfig<#runtime_struct>, )*
+		}
+	};
+
+	// This quote block implements the `RuntimeEvent` enum.
+	let event_impl = quote! {
+		// These are all the events which can be emitted by the runtime.
+		// Note that it is just an accumulation of the events emitted by each pallet, along with
+		// the outcome of every extrinsic reported by system.
+		#[derive(Debug, Clone, PartialEq)]
+		pub enum RuntimeEvent {
+			System(system::Event),
+			#( #pallet_variants(#pallet_names::Event<#runtime_struct>) ),*
+		}
 	};
 
 	// This quote block implements the `RuntimeCall` enum and implements the `Dispatch` trait.
@@ -49,35 +357,72 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		// These are all the calls which are exposed to the world.
 		// Note that it is just an accumulation of the calls exposed by each pallet.
 		//
-		// The parsed function names will be `snake_case`, and that will show up in the enum.
-		#[allow(non_camel_case_types)]
+		// Runtime calls are encoded as the index of the pallet, followed by the pallet call.
+		#[derive(Clone, macros::Encode)]
 		pub enum RuntimeCall {
-			#( #pallet_names(#pallet_names::Call<#runtime_struct>) ),*
+			#(
+				#[codec(index = #call_indices)]
+				#pallet_variants(#pallet_names::Call<#runtime_struct>)
+			),*
+		}
+
+		// Calls can contain other calls, so decoding them is limited to
+		// `support::MAX_EXTRINSIC_DEPTH` levels of nesting.
+		impl crate::codec::Decode for RuntimeCall {
+			fn decode(input: &mut &[u8]) -> Result<Self, crate::codec::Error> {
+				crate::codec::decode_with_depth_limit(crate::support::MAX_EXTRINSIC_DEPTH, input, |input| {
+					match <u8 as crate::codec::Decode>::decode(input)? {
+						#(
+							#call_indices => Ok(RuntimeCall::#pallet_variants(crate::codec::Decode::decode(input)?)),
+						)*
+						_ => Err(crate::codec::Error::Invalid("invalid variant index for `RuntimeCall`")),
+					}
+				})
+			}
+		}
+
+		impl crate::support::GetDispatchInfo for RuntimeCall {
+			fn get_dispatch_info(&self) -> crate::support::DispatchInfo {
+				match self {
+					#(
+						RuntimeCall::#pallet_variants(call) => crate::support::GetDispatchInfo::get_dispatch_info(call),
+					)*
+				}
+			}
 		}
 
 		impl crate::support::Dispatch for #runtime_struct {
-			type Caller = <Runtime as system::Config>::AccountId;
+			type Origin = types::RuntimeOrigin;
 			type Call = RuntimeCall;
-			// Dispatch a call on behalf of a caller. Increments the caller's nonce.
+			// Dispatch a call on behalf of an origin.
 			//
-			// Dispatch allows us to identify which underlying pallet call we want to execute.
-			// Note that we extract the `caller` from the extrinsic, and use that information
-			// to determine who we are executing the call on behalf of.
+			// Dispatch allows us to identify which underlying pallet call we want to execute. Pallet
+			// calls are dispatched with `DispatchIn`, giving them access to the whole runtime.
+			// Note that extrinsics are dispatched with the signed origin of their `caller`, which
+			// determines who we are executing the call on behalf of.
+			//
+			// The call is executed inside a storage transaction, so a failing call leaves no
+			// changes behind in any pallet.
 			fn dispatch(
 				&mut self,
-				caller: Self::Caller,
+				origin: Self::Origin,
 				runtime_call: Self::Call,
-			) -> crate::support::DispatchResult {
-				// This match statement will allow us to correctly route `RuntimeCall`s
-				// to the appropriate pallet level call.
-				match runtime_call {
-					#(
-						RuntimeCall::#pallet_names(call) => {
-							self.#pallet_names.dispatch(caller, call)?;
-						}
-					),*
-				}
-				Ok(())
+			) -> crate::support::DispatchResultWithPostInfo {
+				crate::support::Transactional::with_transaction(self, |runtime| {
+					// This match statement will allow us to correctly route `RuntimeCall`s
+					// to the appropriate pallet level call.
+					match runtime_call {
+						#(
+							RuntimeCall::#pallet_variants(call) => {
+								<#pallet_types as crate::support::DispatchIn<Self>>::dispatch_in(
+									runtime,
+									origin,
+									call,
+								)
+							}
+						),*
+					}
+				})
 			}
 		}
 	};
@@ -85,7 +430,11 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 	// We combine and return all the generated code.
 	quote! {
 		#dispatch_impl
+		#event_impl
+		#genesis_impl
+		#pallet_info_impl
+		#get_pallet_impl
+		#transactional_impl
 		#runtime_impl
 	}
-	.into()
 }
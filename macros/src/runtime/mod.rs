@@ -21,5 +21,5 @@ pub fn runtime(
 
 	// Add our generated code to the end, and return the final result.
 	finished.extend(generated);
-	return finished;
+	finished
 }
@@ -5,6 +5,8 @@ use syn::spanned::Spanned;
 pub struct RuntimeDef {
 	/// This is the name of the struct used by the user. We mostly assume it is `Runtime`.
 	pub runtime_struct: syn::Ident,
+	/// This is the type of the `system` pallet, which must be the first field of the struct.
+	pub system_type: syn::Type,
 	/// This is the list of pallets included in the `Runtime` struct. We omit `system` from this
 	/// list, but during parsing we check that system exists.
 	pub pallets: Vec<(syn::Ident, syn::Type)>,
@@ -23,17 +25,20 @@ impl RuntimeDef {
 		check_system(&item_struct)?;
 
 		let runtime_struct = item_struct.ident;
+		let mut fields = item_struct.fields.into_iter();
+		// `check_system` ensures there is a first field.
+		let system_type = fields.next().expect("checked by check_system").ty;
 
 		// Here is where we will store a list of all the pallets.
 		let mut pallets = vec![];
 		// We skip `system`, which we ensure is the first field in `check_system`.
-		for field in item_struct.fields.into_iter().skip(1) {
+		for field in fields {
 			if let Some(ident) = field.ident {
 				pallets.push((ident, field.ty))
 			}
 		}
 
-		Ok(Self { runtime_struct, pallets })
+		Ok(Self { runtime_struct, system_type, pallets })
 	}
 }
 
@@ -0,0 +1,33 @@
+use super::parse::StorageDef;
+use quote::quote;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_storage(def: StorageDef) -> proc_macro2::TokenStream {
+	let StorageDef { pallet_struct, config_trait, items } = def;
+
+	// The docs and other attributes of each item.
+	let item_attrs = items.iter().map(|item| &item.attrs).collect::<Vec<_>>();
+	// The visibility of each item.
+	let item_vis = items.iter().map(|item| &item.vis).collect::<Vec<_>>();
+	// The accessor function of each item, i.e. the item name in `snake_case`.
+	let item_fn = items.iter().map(|item| crate::utils::snake_case(&item.name)).collect::<Vec<_>>();
+	// The item names as strings, which prefix the keys of each item along with the pallet name.
+	let item_name = items.iter().map(|item| item.name.to_string()).collect::<Vec<_>>();
+	// The storage type of each item.
+	let item_type = items.iter().map(|item| &item.ty).collect::<Vec<_>>();
+
+	quote! {
+		impl<T: #config_trait> #pallet_struct<T> {
+			#(
+				#( #item_attrs )*
+				#item_vis fn #item_fn(&self) -> #item_type {
+					// The keys of the item are prefixed with the name of the pallet in the runtime,
+					// as given by `PalletInfo`, and the name of the item.
+					let pallet = <T::PalletInfo as crate::support::PalletInfo>::name::<Self>()
+						.expect("pallet is part of the runtime");
+					<#item_type>::new(self.storage.clone(), crate::storage::prefix(pallet, #item_name))
+				}
+			)*
+		}
+	}
+}
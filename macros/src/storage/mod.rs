@@ -0,0 +1,19 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn storage(
+	_attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	// Unlike our other macros, the storage items are not valid Rust on their own, so we replace
+	// them with the generated code rather than keeping them.
+	let item_impl = syn::parse_macro_input!(item as syn::Item);
+
+	// First we parse the storage items of the pallet...
+	match parse::StorageDef::try_from(item_impl) {
+		// ..then we generate our new code.
+		Ok(def) => expand::expand_storage(def).into(),
+		Err(e) => e.to_compile_error().into(),
+	}
+}
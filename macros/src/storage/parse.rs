@@ -0,0 +1,65 @@
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the storage items
+/// of a pallet.
+#[derive(Debug)]
+pub struct StorageDef {
+	/// This is the name of the pallet struct holding the storage. We mostly assume it is `Pallet`.
+	pub pallet_struct: syn::Ident,
+	/// This is the name of the config trait of the pallet, taken from the `impl<T: Config>`
+	/// generics. We mostly assume it is `Config`.
+	pub config_trait: syn::Path,
+	/// This is a list of the storage items of this pallet. See `StorageItemDef`.
+	pub items: Vec<StorageItemDef>,
+}
+
+/// This is the metadata we keep about each storage item in our pallet.
+#[derive(Debug)]
+pub struct StorageItemDef {
+	/// The attributes of the item, such as its docs, which we keep on its accessor.
+	pub attrs: Vec<syn::Attribute>,
+	/// The visibility of the item, which we keep on its accessor.
+	pub vis: syn::Visibility,
+	/// The item name, in `CamelCase`.
+	pub name: syn::Ident,
+	/// The type of the item, e.g. `StorageMap<T::AccountId, T::Balance>`.
+	pub ty: syn::Type,
+}
+
+impl StorageDef {
+	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+		// First we check that we are parsing an `impl`.
+		let item_impl = if let syn::Item::Impl(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid pallet::storage, expected item impl"))
+		};
+
+		// Extract the name of the struct, like we do for calls.
+		let pallet_struct = match &*item_impl.self_ty {
+			syn::Type::Path(tp) => tp.path.segments.first().unwrap().ident.clone(),
+			_ => return Err(syn::Error::new(item_impl.self_ty.span(), "Invalid pallet struct")),
+		};
+		let config_trait = crate::utils::config_trait(&item_impl.generics)?;
+
+		// Every item of the `impl` should be a storage item, declared as a type alias.
+		let mut items = vec![];
+		for item in item_impl.items {
+			let item_type = match item {
+				syn::ImplItem::Type(item_type) if item_type.generics.params.is_empty() => item_type,
+				item => {
+					let msg = "Invalid pallet::storage, expected `type Name = StorageType<..>;`";
+					return Err(syn::Error::new(item.span(), msg))
+				},
+			};
+			items.push(StorageItemDef {
+				attrs: item_type.attrs,
+				vis: item_type.vis,
+				name: item_type.ident,
+				ty: item_type.ty,
+			});
+		}
+
+		Ok(Self { pallet_struct, config_trait, items })
+	}
+}
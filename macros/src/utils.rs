@@ -0,0 +1,45 @@
+use syn::spanned::Spanned;
+
+/// Extract the config trait from pallet generics, which are expected to be exactly `<T: Config>`.
+///
+/// The trait does not need to be named `Config`, so e.g. `<T: BalancesConfig>` is supported too.
+pub fn config_trait(generics: &syn::Generics) -> syn::Result<syn::Path> {
+	let msg = "Invalid pallet generics, expected `<T: Config>`";
+	let param = match generics.type_params().next() {
+		Some(param) if generics.params.len() == 1 => param,
+		_ => return Err(syn::Error::new(generics.span(), msg)),
+	};
+
+	match param.bounds.first() {
+		Some(syn::TypeParamBound::Trait(bound)) if param.bounds.len() == 1 => Ok(bound.path.clone()),
+		_ => Err(syn::Error::new(param.span(), msg)),
+	}
+}
+
+/// Convert a `snake_case` identifier into `CamelCase`, e.g. `create_claim` into `CreateClaim`.
+pub fn camel_case(ident: &syn::Ident) -> syn::Ident {
+	let name = ident
+		.to_string()
+		.split('_')
+		.map(|word| {
+			let mut chars = word.chars();
+			match chars.next() {
+				Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+				None => String::new(),
+			}
+		})
+		.collect::<String>();
+	syn::Ident::new(&name, ident.span())
+}
+
+/// Convert a `CamelCase` identifier into `snake_case`, e.g. `AccountNonce` into `account_nonce`.
+pub fn snake_case(ident: &syn::Ident) -> syn::Ident {
+	let mut name = String::new();
+	for (i, c) in ident.to_string().chars().enumerate() {
+		if c.is_uppercase() && i > 0 {
+			name.push('_');
+		}
+		name.extend(c.to_lowercase());
+	}
+	syn::Ident::new(&name, ident.span())
+}
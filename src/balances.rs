@@ -1,134 +1,813 @@
 use crate::{
-	support::DispatchResult,
+	storage::{Storage, StorageMap, StorageValue},
+	support::{
+		ArithmeticError, Currency, DispatchError, DispatchResult, ExistenceRequirement, Hooks,
+		ReservableCurrency, TransactionalVec, Weight, ensure_root, ensure_signed,
+	},
+	trie,
 	types::{
-		BalancesConfig, BalancesPallet, Runtime, SystemConfig,
-		types::{AccountId, Balance, BlockNumber, Nonce},
+		BalancesConfig, BalancesPallet, Runtime, RuntimeEvent, SystemConfig, SystemPallet,
+		types::{AccountId, Balance, BlockNumber, Nonce, RuntimeOrigin},
 	},
 };
-use num::traits::{CheckedAdd, CheckedSub, Zero};
+use core::fmt::Debug;
+use num::traits::{CheckedAdd, CheckedSub, Saturating, Zero};
 use std::collections::BTreeMap;
 
 impl SystemConfig for Runtime {
+	type PalletInfo = Self;
+	type RuntimeEvent = RuntimeEvent;
+	type RuntimeOrigin = RuntimeOrigin;
 	type AccountId = AccountId;
 	type BlockNumber = BlockNumber;
 	type Nonce = Nonce;
+	/// Room for about a hundred transfers.
+	const MAXIMUM_BLOCK_WEIGHT: Weight = 1_000_000;
+	const MORTALITY_PERIOD: BlockNumber = 256;
 }
 
 impl BalancesConfig for Runtime {
 	type Balance = Balance;
+	const EXISTENTIAL_DEPOSIT: Balance = 1_000;
+}
+
+/// The genesis configuration of the balances pallet.
+#[derive(serde::Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct GenesisConfig<T: BalancesConfig> {
+	/// The initial balance of each endowed account.
+	pub balances: Vec<(T::AccountId, T::Balance)>,
+}
+
+impl<T: BalancesConfig> Default for GenesisConfig<T> {
+	fn default() -> Self {
+		Self { balances: Vec::new() }
+	}
 }
 
 impl<T: BalancesConfig> BalancesPallet<T> {
-	pub fn new() -> Self {
-		Self { balances: BTreeMap::new() }
+	pub fn new(storage: Storage) -> Self {
+		Self { storage, events: TransactionalVec::new() }
+	}
+
+	/// Set up the genesis state of the pallet. Fails if the total issuance would overflow.
+	pub fn build_genesis(&mut self, config: &GenesisConfig<T>) -> DispatchResult {
+		for (who, amount) in &config.balances {
+			self.set_balance(who, *amount)?;
+		}
+		Ok(())
+	}
+
+	/// The storage key of the balance of `who`.
+	pub fn balance_key(&self, who: &T::AccountId) -> Vec<u8> {
+		self.balances().key(who)
+	}
+
+	/// Set the balance of `who` to `amount`, reaping the account if it is below the existential
+	/// deposit. The total issuance follows, and the balance is left untouched if it would overflow.
+	pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		self.issuance()
+			.checked_sub(&self.balance(who))
+			.and_then(|issuance| issuance.checked_add(&amount))
+			.ok_or(ArithmeticError::Overflow)?;
+		self.write_balance(who, amount);
+		Ok(())
+	}
+
+	/// Write the free balance of `who`, keeping the total issuance in step. The account is reaped
+	/// if `amount` is below the existential deposit, in which case `amount` is lost and returned.
+	///
+	/// Every change of a free balance goes through here, once the caller has made sure the total
+	/// issuance can not overflow.
+	fn write_balance(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+		if amount < T::EXISTENTIAL_DEPOSIT {
+			self.reap_account(who);
+			return amount;
+		}
+		let issuance = self.issuance().saturating_sub(self.balance(who)).saturating_add(amount);
+		self.total_issuance().insert(&issuance);
+		self.balances().insert(who, &amount);
+		T::Balance::zero()
+	}
+
+	/// Write the reserved balance of `who`, keeping the total issuance in step.
+	///
+	/// Every change of a reserved balance goes through here, once the caller has made sure the
+	/// total issuance can not overflow.
+	fn write_reserved(&mut self, who: &T::AccountId, amount: T::Balance) {
+		let issuance = self
+			.issuance()
+			.saturating_sub(self.reserved_balance(who))
+			.saturating_add(amount);
+		self.total_issuance().insert(&issuance);
+		if amount.is_zero() {
+			self.reserved().remove(who);
+		} else {
+			self.reserved().insert(who, &amount);
+		}
 	}
 
-	pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
-		self.balances.insert(who.clone(), amount);
+	/// Remove `who` from the state, destroying all of its balance, along with its nonce kept by the
+	/// system pallet.
+	fn reap_account(&mut self, who: &T::AccountId) {
+		let issuance = self.issuance().saturating_sub(self.balance(who));
+		self.total_issuance().insert(&issuance);
+		self.balances().remove(who);
+		self.write_reserved(who, T::Balance::zero());
+		// The system pallet keeps its storage items in the same storage as this pallet.
+		SystemPallet::<T>::new(self.storage.clone()).kill_account(who);
 	}
 
+	/// The free balance of `who`, which does not include its reserved balance.
 	pub fn balance(&self, who: &T::AccountId) -> T::Balance {
-		*self.balances.get(who).unwrap_or(&T::Balance::zero())
+		self.balances().get(who).unwrap_or(T::Balance::zero())
 	}
 
+	/// The total amount of currency in existence, i.e. the sum of all free and reserved balances.
+	pub fn issuance(&self) -> T::Balance {
+		self.total_issuance().get().unwrap_or(T::Balance::zero())
+	}
+
+	/// Whether `who` has reserved funds, which reaping the account would destroy, so it must be
+	/// kept alive whatever the existence requirement.
+	fn has_encumbered_funds(&self, who: &T::AccountId) -> bool {
+		!self.reserved_balance(who).is_zero()
+	}
+
+	/// Check that the free balance of `who` can drop to `new_balance`. It can not drop below the
+	/// existential deposit if the account must be kept alive, which it must if it has reserved
+	/// funds.
+	fn ensure_can_withdraw(
+		&self,
+		who: &T::AccountId,
+		new_balance: T::Balance,
+		existence: ExistenceRequirement,
+	) -> DispatchResult {
+		let keep_alive =
+			existence == ExistenceRequirement::KeepAlive || self.has_encumbered_funds(who);
+		if new_balance < T::EXISTENTIAL_DEPOSIT && keep_alive {
+			return Err(Error::<T>::KeepAlive.into());
+		}
+		Ok(())
+	}
+
+	/// A proof of the balance of `who` against the current state root. Accounts which never had a
+	/// balance are proven absent, which a light client should read as a zero balance.
+	pub fn balance_proof(&self, who: &T::AccountId) -> trie::Proof {
+		self.storage.prove(&self.balance_key(who))
+	}
+}
+
+#[macros::storage]
+impl<T: BalancesConfig> BalancesPallet<T> {
+	/// The balance of every existing account, i.e. with at least the existential deposit.
+	type Balances = StorageMap<T::AccountId, T::Balance>;
+	/// The reserved balance of every account which has one.
+	type Reserved = StorageMap<T::AccountId, T::Balance>;
+	/// The sum of all free and reserved balances.
+	type TotalIssuance = StorageValue<T::Balance>;
+}
+
+#[macros::error(BalancesPallet)]
+pub enum Error<T: BalancesConfig> {
+	/// The sender does not have enough funds.
+	InsufficientBalance,
+	/// The account would be left with less than the existential deposit.
+	ExistentialDeposit,
+	/// The sender would be reaped, but must be kept alive, e.g. because it has reserved funds.
+	KeepAlive,
+	/// The beneficiary account does not exist.
+	DeadAccount,
+}
+
+#[macros::event(BalancesPallet)]
+pub enum Event<T: BalancesConfig> {
+	/// `amount` was transferred from `from` to `to`.
+	Transfer { from: T::AccountId, to: T::AccountId, amount: T::Balance },
+	/// The balance of `who` was set to `amount` by root.
+	BalanceSet { who: T::AccountId, amount: T::Balance },
+	/// `account` was reaped, losing the `amount` left below the existential deposit.
+	DustLost { account: T::AccountId, amount: T::Balance },
+	/// `amount` was created into the balance of `who` by root.
+	Minted { who: T::AccountId, amount: T::Balance },
+	/// `amount` was destroyed from the balance of `who` by root.
+	Burned { who: T::AccountId, amount: T::Balance },
+	/// `amount` of the free balance of `who` was reserved.
+	Reserved { who: T::AccountId, amount: T::Balance },
+	/// `amount` of the reserved balance of `who` was returned to its free balance.
+	Unreserved { who: T::AccountId, amount: T::Balance },
+	/// `amount` of the reserved balance of `from` was moved to the reserved balance of `to`.
+	ReserveRepatriated { from: T::AccountId, to: T::AccountId, amount: T::Balance },
+}
+
+#[macros::call]
+impl<T: BalancesConfig> BalancesPallet<T> {
+	/// Transfer `amount` from the caller to `to`. The caller is reaped if this leaves them below
+	/// the existential deposit.
+	#[weight(10_000)]
 	pub fn transfer(
 		&mut self,
-		from: &T::AccountId,
-		to: &T::AccountId,
+		origin: T::RuntimeOrigin,
+		to: T::AccountId,
 		amount: T::Balance,
 	) -> DispatchResult {
-		let caller_balance = self.balance(&from);
-		let to_balance = self.balance(&to);
+		let caller = ensure_signed(origin)?;
+		Currency::transfer(self, &caller, &to, amount, ExistenceRequirement::AllowDeath)
+	}
 
-		let new_from_balance = caller_balance.checked_sub(&amount).ok_or("Not enough funds.")?;
-		let new_to_balance = to_balance.checked_add(&amount).ok_or("Funds overflow")?;
+	/// Transfer `amount` from the caller to `to`, failing if this would reap the caller.
+	#[weight(10_000)]
+	pub fn transfer_keep_alive(
+		&mut self,
+		origin: T::RuntimeOrigin,
+		to: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let caller = ensure_signed(origin)?;
+		Currency::transfer(self, &caller, &to, amount, ExistenceRequirement::KeepAlive)
+	}
 
-		self.set_balance(from, new_from_balance);
-		self.set_balance(to, new_to_balance);
+	/// Transfer all of the free balance of the caller to `to`, reaping the caller. If
+	/// `keep_alive`, or if the caller has reserved funds, the existential deposit is kept.
+	#[weight(10_000)]
+	pub fn transfer_all(
+		&mut self,
+		origin: T::RuntimeOrigin,
+		to: T::AccountId,
+		keep_alive: bool,
+	) -> DispatchResult {
+		let caller = ensure_signed(origin)?;
+		let kept = match keep_alive || self.has_encumbered_funds(&caller) {
+			true => T::EXISTENTIAL_DEPOSIT,
+			false => T::Balance::zero(),
+		};
+		let amount = self.balance(&caller).saturating_sub(kept);
+		let existence = match keep_alive {
+			true => ExistenceRequirement::KeepAlive,
+			false => ExistenceRequirement::AllowDeath,
+		};
+		Currency::transfer(self, &caller, &to, amount, existence)
+	}
 
+	/// Set the balance of `who` to `amount`, reaping the account if it is below the existential
+	/// deposit. Only callable by root.
+	#[weight(10_000)]
+	pub fn force_set_balance(
+		&mut self,
+		origin: T::RuntimeOrigin,
+		who: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		ensure_root(origin)?;
+		self.set_balance(&who, amount)?;
+		self.deposit_event(Event::BalanceSet { who, amount });
+		Ok(())
+	}
+
+	/// Create `amount` into the balance of `who`, raising the total issuance. Only callable by
+	/// root.
+	#[weight(10_000)]
+	pub fn mint(
+		&mut self,
+		origin: T::RuntimeOrigin,
+		who: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		ensure_root(origin)?;
+		self.deposit_creating(&who, amount)?;
+		self.deposit_event(Event::Minted { who, amount });
 		Ok(())
 	}
-}
 
-#[cfg_attr(not(test), allow(dead_code))]
-pub enum Call<T: BalancesConfig> {
-	Transfer { to: T::AccountId, amount: T::Balance },
+	/// Destroy `amount` from the balance of `who`, lowering the total issuance. The account is
+	/// reaped if this leaves it below the existential deposit. Only callable by root.
+	#[weight(10_000)]
+	pub fn burn(
+		&mut self,
+		origin: T::RuntimeOrigin,
+		who: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		ensure_root(origin)?;
+		self.withdraw(&who, amount, ExistenceRequirement::AllowDeath)?;
+		self.deposit_event(Event::Burned { who, amount });
+		Ok(())
+	}
 }
 
-/// Implementation of the dispatch logic, mapping from `BalancesCall` to the appropriate underlying
-/// function we want to execute.
-impl<T: BalancesConfig> crate::support::Dispatch for BalancesPallet<T> {
-	type Caller = T::AccountId;
-	type Call = Call<T>;
+impl<T: BalancesConfig> Currency<T::AccountId> for BalancesPallet<T> {
+	type Balance = T::Balance;
+
+	/// Both accounts are checked against the existential deposit.
+	fn transfer(
+		&mut self,
+		from: &T::AccountId,
+		to: &T::AccountId,
+		value: T::Balance,
+		existence: ExistenceRequirement,
+	) -> DispatchResult {
+		// Withdrawing first could reap the sender before the same funds are deposited back.
+		if from == to {
+			return Ok(());
+		}
+		self.withdraw(from, value, existence)?;
+		// The sender is already debited here. If crediting the recipient fails, the storage
+		// transaction wrapping the dispatch rolls the debit back.
+		self.deposit_creating(to, value)?;
+
+		self.deposit_event(Event::Transfer { from: from.clone(), to: to.clone(), amount: value });
+		Ok(())
+	}
 
-	fn dispatch(
+	/// If this leaves `who` below the existential deposit, the account is reaped and whatever is
+	/// left of its balance is lost, unless `existence` requires it to be kept alive. Either way, the
+	/// total issuance goes down by what leaves the account.
+	fn withdraw(
 		&mut self,
-		caller: Self::Caller,
-		call: Self::Call,
-	) -> crate::support::DispatchResult {
-		match call {
-			Call::Transfer { to, amount } => {
-				self.transfer(&caller, &to, amount)?;
-			},
+		who: &T::AccountId,
+		amount: T::Balance,
+		existence: ExistenceRequirement,
+	) -> DispatchResult {
+		let new_balance =
+			self.balance(who).checked_sub(&amount).ok_or(Error::<T>::InsufficientBalance)?;
+		self.ensure_can_withdraw(who, new_balance, existence)?;
+		let lost = self.write_balance(who, new_balance);
+		if !lost.is_zero() {
+			self.deposit_event(Event::DustLost { account: who.clone(), amount: lost });
+		}
+		Ok(())
+	}
+
+	/// The new balance must be at least the existential deposit. The total issuance goes up by
+	/// `amount`.
+	fn deposit_creating(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		let new_balance =
+			self.balance(who).checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+		self.issuance().checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+		if new_balance < T::EXISTENTIAL_DEPOSIT {
+			return Err(Error::<T>::ExistentialDeposit.into());
 		}
+		self.write_balance(who, new_balance);
+		Ok(())
+	}
+}
+
+impl<T: BalancesConfig> ReservableCurrency<T::AccountId> for BalancesPallet<T> {
+	fn can_reserve(&self, who: &T::AccountId, value: T::Balance) -> bool {
+		self.balance(who).checked_sub(&value).is_some_and(|new_balance| {
+			self.ensure_can_withdraw(who, new_balance, ExistenceRequirement::KeepAlive)
+				.is_ok()
+		})
+	}
+
+	fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+		self.reserved().get(who).unwrap_or(T::Balance::zero())
+	}
+
+	/// Reserving can not reap `who`.
+	fn reserve(&mut self, who: &T::AccountId, value: T::Balance) -> DispatchResult {
+		let new_balance =
+			self.balance(who).checked_sub(&value).ok_or(Error::<T>::InsufficientBalance)?;
+		self.ensure_can_withdraw(who, new_balance, ExistenceRequirement::KeepAlive)?;
+		// The funds are only moved, so the sum of both balances stays within the total issuance.
+		let reserved = self.reserved_balance(who).saturating_add(value);
+		self.write_balance(who, new_balance);
+		self.write_reserved(who, reserved);
+		self.deposit_event(Event::Reserved { who: who.clone(), amount: value });
 		Ok(())
 	}
+
+	fn unreserve(&mut self, who: &T::AccountId, value: T::Balance) -> T::Balance {
+		let reserved = self.reserved_balance(who);
+		let amount = value.min(reserved);
+		if amount.is_zero() {
+			return value;
+		}
+		// Accounts with reserved funds are never reaped, so `who` still exists.
+		let balance = self.balance(who).saturating_add(amount);
+		self.write_reserved(who, reserved.saturating_sub(amount));
+		self.write_balance(who, balance);
+		self.deposit_event(Event::Unreserved { who: who.clone(), amount });
+		value.saturating_sub(amount)
+	}
+
+	/// The beneficiary must already exist.
+	fn repatriate_reserved(
+		&mut self,
+		slashed: &T::AccountId,
+		beneficiary: &T::AccountId,
+		value: T::Balance,
+	) -> Result<T::Balance, DispatchError> {
+		if slashed == beneficiary {
+			return Ok(value.saturating_sub(self.reserved_balance(slashed)));
+		}
+		if self.balance(beneficiary) < T::EXISTENTIAL_DEPOSIT {
+			return Err(Error::<T>::DeadAccount.into());
+		}
+		let reserved = self.reserved_balance(slashed);
+		let amount = value.min(reserved);
+		// The funds are only moved, so the balance of the beneficiary stays within the total
+		// issuance.
+		self.write_reserved(slashed, reserved.saturating_sub(amount));
+		let reserved = self.reserved_balance(beneficiary).saturating_add(amount);
+		self.write_reserved(beneficiary, reserved);
+		self.deposit_event(Event::ReserveRepatriated {
+			from: slashed.clone(),
+			to: beneficiary.clone(),
+			amount,
+		});
+		Ok(value.saturating_sub(amount))
+	}
+}
+
+impl<T: BalancesConfig> Hooks<T, T::BlockNumber> for BalancesPallet<T> {}
+
+impl<T: BalancesConfig> Debug for BalancesPallet<T>
+where
+	T::AccountId: Debug,
+	T::Balance: Debug,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("BalancesPallet")
+			.field("balances", &BTreeMap::from_iter(self.balances().iter()))
+			.field("reserved", &BTreeMap::from_iter(self.reserved().iter()))
+			.field("events", &self.events)
+			.finish()
+	}
 }
 
 #[cfg(test)]
 mod tests {
-	use crate::support::Dispatch;
-	use crate::types::SystemConfig;
+	use crate::storage::Storage;
+	use crate::support::{
+		ArithmeticError, Currency, Dispatch, DispatchError, ExistenceRequirement, RawOrigin,
+		ReservableCurrency,
+	};
+	use crate::types::{BalancesConfig, BalancesPallet, SystemConfig, SystemPallet};
+	use num::traits::{CheckedAdd, Saturating, Zero};
 
 	struct TestConfig;
 	impl SystemConfig for TestConfig {
+		type PalletInfo = ();
+		type RuntimeEvent = ();
+		type RuntimeOrigin = crate::support::RawOrigin<String>;
 		type AccountId = String;
 		type BlockNumber = u32;
 		type Nonce = u32;
+		const MAXIMUM_BLOCK_WEIGHT: u64 = u64::MAX;
+		const MORTALITY_PERIOD: u32 = 256;
 	}
 
 	impl super::BalancesConfig for TestConfig {
 		type Balance = u128;
+		const EXISTENTIAL_DEPOSIT: u128 = 10;
+	}
+
+	impl<T: BalancesConfig> BalancesPallet<T> {
+		/// Assert that the total issuance is the sum of all free and reserved balances, which every
+		/// change of a balance must keep true, e.g. after every block.
+		pub fn assert_total_issuance(&self) {
+			let sum = self
+				.balances()
+				.iter()
+				.chain(self.reserved().iter())
+				.try_fold(T::Balance::zero(), |sum, (_, balance)| sum.checked_add(&balance));
+			assert!(
+				sum == Some(self.issuance()),
+				"the total issuance is not the sum of all balances"
+			);
+		}
+
+		/// Destroy up to `value` of the reserved balance of `who`, as a pallet punishing `who`
+		/// would. Returns the part of `value` which could not be slashed.
+		pub fn slash_reserved(&mut self, who: &T::AccountId, value: T::Balance) -> T::Balance {
+			let reserved = self.reserved_balance(who);
+			self.write_reserved(who, reserved.saturating_sub(value));
+			value.saturating_sub(reserved)
+		}
 	}
 
 	#[test]
 	fn init_balances() {
-		let mut balances = super::BalancesPallet::<TestConfig>::new();
+		let mut balances = super::BalancesPallet::<TestConfig>::new(Storage::new());
 
 		assert_eq!(balances.balance(&"alice".to_string()), 0);
-		balances.set_balance(&"alice".to_string(), 100);
+		balances.set_balance(&"alice".to_string(), 100).unwrap();
 		assert_eq!(balances.balance(&"alice".to_string()), 100);
 		assert_eq!(balances.balance(&"bob".to_string()), 0);
 	}
 
 	#[test]
 	fn transfer_funds() {
-		let mut balances = super::BalancesPallet::<TestConfig>::new();
+		let mut balances = super::BalancesPallet::<TestConfig>::new(Storage::new());
 
-		balances.set_balance(&"alice".to_string(), 100);
+		balances.set_balance(&"alice".to_string(), 100).unwrap();
 
 		// replaced direct call with dispatch
 		let call = super::Call::<TestConfig>::Transfer { to: "bob".to_string(), amount: 50 };
-		let result = balances.dispatch("alice".to_string(), call);
+		let result = balances.dispatch(RawOrigin::Signed("alice".to_string()), call);
 
-		assert_eq!(result.unwrap(), ());
+		assert_eq!(result, Ok(().into()));
 		assert_eq!(balances.balance(&"alice".to_string()), 50);
 		assert_eq!(balances.balance(&"bob".to_string()), 50);
+		assert_eq!(
+			balances.take_events(),
+			vec![super::Event::Transfer {
+				from: "alice".to_string(),
+				to: "bob".to_string(),
+				amount: 50
+			}]
+		);
+	}
+
+	#[test]
+	fn transfer_to_self_is_a_no_op() {
+		let mut balances = super::BalancesPallet::<TestConfig>::new(Storage::new());
+		let alice = "alice".to_string();
+		balances.set_balance(&alice, 15).unwrap();
+
+		// Leaving less than the existential deposit behind must not reap the account.
+		let call = super::Call::<TestConfig>::Transfer { to: alice.clone(), amount: 10 };
+		let result = balances.dispatch(RawOrigin::Signed(alice.clone()), call);
+
+		assert_eq!(result, Ok(().into()));
+		assert_eq!(balances.balance(&alice), 15);
+		assert_eq!(balances.issuance(), 15);
+		assert_eq!(balances.take_events(), vec![]);
 	}
 
 	#[test]
 	fn fail_to_transfer_non_existent_funds() {
-		let mut balances = super::BalancesPallet::<TestConfig>::new();
+		let mut balances = super::BalancesPallet::<TestConfig>::new(Storage::new());
 
-		balances.set_balance(&"alice".to_string(), 100);
+		balances.set_balance(&"alice".to_string(), 100).unwrap();
 
 		// replaced direct call with dispatch
 		let call = super::Call::<TestConfig>::Transfer { to: "bob".to_string(), amount: 101 };
-		let result = balances.dispatch("alice".to_string(), call);
+		let result = balances.dispatch(RawOrigin::Signed("alice".to_string()), call);
 
 		assert!(result.is_err());
-		assert_eq!(result.unwrap_err(), "Not enough funds.");
+		assert_eq!(result, Err(super::Error::<TestConfig>::InsufficientBalance.into()));
 		assert_eq!(balances.balance(&"alice".to_string()), 100);
 		assert_eq!(balances.balance(&"bob".to_string()), 0);
 	}
+
+	#[test]
+	fn failed_transfer_rolls_back_debit() {
+		let mut balances = super::BalancesPallet::<TestConfig>::new(Storage::new());
+
+		balances.set_balance(&"alice".to_string(), 100).unwrap();
+
+		// Alice is debited before crediting Bob fails, since it would not create his account.
+		let call = super::Call::<TestConfig>::Transfer { to: "bob".to_string(), amount: 5 };
+		let result = balances.dispatch(RawOrigin::Signed("alice".to_string()), call);
+
+		assert_eq!(result, Err(super::Error::<TestConfig>::ExistentialDeposit.into()));
+		assert_eq!(balances.balance(&"alice".to_string()), 100);
+		assert_eq!(balances.balance(&"bob".to_string()), 0);
+		assert!(balances.take_events().is_empty());
+	}
+
+	#[test]
+	fn only_root_can_force_set_balance() {
+		let mut balances = super::BalancesPallet::<TestConfig>::new(Storage::new());
+		let call =
+			|| super::Call::<TestConfig>::ForceSetBalance { who: "alice".to_string(), amount: 100 };
+
+		let result = balances.dispatch(RawOrigin::Signed("alice".to_string()), call());
+		assert_eq!(result, Err(DispatchError::BadOrigin.into()));
+		assert_eq!(balances.balance(&"alice".to_string()), 0);
+
+		assert_eq!(balances.dispatch(RawOrigin::Root, call()), Ok(().into()));
+		assert_eq!(balances.balance(&"alice".to_string()), 100);
+		assert_eq!(
+			balances.take_events(),
+			vec![super::Event::BalanceSet { who: "alice".to_string(), amount: 100 }]
+		);
+
+		// The total issuance can not overflow, at genesis either.
+		let call = super::Call::<TestConfig>::ForceSetBalance {
+			who: "bob".to_string(),
+			amount: u128::MAX,
+		};
+		let result = balances.dispatch(RawOrigin::Root, call);
+		assert_eq!(result, Err(ArithmeticError::Overflow.into()));
+		assert_eq!(balances.balance(&"bob".to_string()), 0);
+		assert_eq!(balances.issuance(), 100);
+		let genesis = super::GenesisConfig { balances: vec![("bob".to_string(), u128::MAX)] };
+		assert_eq!(balances.build_genesis(&genesis), Err(ArithmeticError::Overflow.into()));
+		assert_eq!(balances.issuance(), 100);
+
+		// Only signed origins can transfer.
+		let call = super::Call::<TestConfig>::Transfer { to: "bob".to_string(), amount: 10 };
+		let result = balances.dispatch(RawOrigin::Root, call);
+		assert_eq!(result, Err(DispatchError::BadOrigin.into()));
+	}
+
+	#[test]
+	fn accounts_below_the_existential_deposit_are_reaped() {
+		let storage = Storage::new();
+		let mut balances = super::BalancesPallet::<TestConfig>::new(storage.clone());
+		let mut system = SystemPallet::<TestConfig>::new(storage);
+		let alice = "alice".to_string();
+		balances.set_balance(&alice, 100).unwrap();
+		system.inc_nonce(&alice);
+
+		// Transfers can not create accounts below the existential deposit...
+		let call = super::Call::<TestConfig>::Transfer { to: "bob".to_string(), amount: 5 };
+		let result = balances.dispatch(RawOrigin::Signed(alice.clone()), call);
+		assert_eq!(result, Err(super::Error::<TestConfig>::ExistentialDeposit.into()));
+
+		// ...nor leave the sender below it when it must be kept alive...
+		let call =
+			super::Call::<TestConfig>::TransferKeepAlive { to: "bob".to_string(), amount: 95 };
+		let result = balances.dispatch(RawOrigin::Signed(alice.clone()), call);
+		assert_eq!(result, Err(super::Error::<TestConfig>::KeepAlive.into()));
+		assert_eq!(balances.balance(&alice), 100);
+
+		// ...but otherwise reap the sender, removing its nonce and losing what is left.
+		let call = super::Call::<TestConfig>::Transfer { to: "bob".to_string(), amount: 95 };
+		assert_eq!(balances.dispatch(RawOrigin::Signed(alice.clone()), call), Ok(().into()));
+		assert_eq!(balances.balance(&"bob".to_string()), 95);
+		assert!(!balances.balances().contains_key(&alice));
+		assert_eq!(system.nonce(&alice), 0);
+		assert_eq!(
+			balances.take_events(),
+			vec![
+				super::Event::DustLost { account: alice.clone(), amount: 5 },
+				super::Event::Transfer { from: alice, to: "bob".to_string(), amount: 95 },
+			]
+		);
+
+		// Setting a balance below the existential deposit reaps the account too.
+		balances.set_balance(&"bob".to_string(), 9).unwrap();
+		assert!(!balances.balances().contains_key(&"bob".to_string()));
+	}
+
+	#[test]
+	fn transfer_all() {
+		let mut balances = super::BalancesPallet::<TestConfig>::new(Storage::new());
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		balances.set_balance(&alice, 100).unwrap();
+
+		// Keeping alice alive leaves her with the existential deposit.
+		let call = super::Call::<TestConfig>::TransferAll { to: bob.clone(), keep_alive: true };
+		assert_eq!(balances.dispatch(RawOrigin::Signed(alice.clone()), call), Ok(().into()));
+		assert_eq!(balances.balance(&alice), 10);
+		assert_eq!(balances.balance(&bob), 90);
+
+		let call = super::Call::<TestConfig>::TransferAll { to: bob.clone(), keep_alive: false };
+		assert_eq!(balances.dispatch(RawOrigin::Signed(alice.clone()), call), Ok(().into()));
+		assert!(!balances.balances().contains_key(&alice));
+		assert_eq!(balances.balance(&bob), 100);
+	}
+
+	#[test]
+	fn total_issuance_follows_every_balance_change() {
+		let mut balances = super::BalancesPallet::<TestConfig>::new(Storage::new());
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		balances.set_balance(&alice, 100).unwrap();
+		balances.set_balance(&bob, 50).unwrap();
+		assert_eq!(balances.issuance(), 150);
+
+		// Transfers move currency around, but the dust of reaped accounts is lost.
+		let call = super::Call::<TestConfig>::Transfer { to: bob.clone(), amount: 95 };
+		assert_eq!(balances.dispatch(RawOrigin::Signed(alice.clone()), call), Ok(().into()));
+		assert_eq!(balances.issuance(), 145);
+		balances.assert_total_issuance();
+
+		// Only root can mint and burn.
+		let mint = |amount| super::Call::<TestConfig>::Mint { who: alice.clone(), amount };
+		let result = balances.dispatch(RawOrigin::Signed(alice.clone()), mint(55));
+		assert_eq!(result, Err(DispatchError::BadOrigin.into()));
+		assert_eq!(balances.dispatch(RawOrigin::Root, mint(55)), Ok(().into()));
+		assert_eq!(balances.issuance(), 200);
+		let result = balances.dispatch(RawOrigin::Root, mint(u128::MAX - 199));
+		assert_eq!(result, Err(ArithmeticError::Overflow.into()));
+
+		let call = super::Call::<TestConfig>::Burn { who: bob.clone(), amount: 140 };
+		assert_eq!(balances.dispatch(RawOrigin::Root, call), Ok(().into()));
+		assert_eq!(balances.balance(&bob), 0);
+		assert_eq!(balances.issuance(), 55);
+		balances.assert_total_issuance();
+		assert_eq!(
+			balances.take_events()[2..],
+			[
+				super::Event::Minted { who: alice, amount: 55 },
+				super::Event::DustLost { account: bob.clone(), amount: 5 },
+				super::Event::Burned { who: bob, amount: 140 },
+			]
+		);
+	}
+
+	#[test]
+	fn reserved_funds_can_not_be_spent() {
+		let mut balances = super::BalancesPallet::<TestConfig>::new(Storage::new());
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		balances.set_balance(&alice, 100).unwrap();
+		balances.set_balance(&bob, 100).unwrap();
+
+		// Reserving can not reap the account.
+		assert!(!balances.can_reserve(&alice, 95));
+		assert_eq!(balances.reserve(&alice, 95), Err(super::Error::<TestConfig>::KeepAlive.into()));
+		assert!(balances.can_reserve(&alice, 60));
+		assert_eq!(balances.reserve(&alice, 60), Ok(()));
+		assert_eq!(balances.balance(&alice), 40);
+		assert_eq!(balances.reserved_balance(&alice), 60);
+		assert_eq!(balances.issuance(), 200);
+
+		// Reserved funds can not be transferred, and keep the account alive.
+		let call = super::Call::<TestConfig>::Transfer { to: bob.clone(), amount: 50 };
+		let result = balances.dispatch(RawOrigin::Signed(alice.clone()), call);
+		assert_eq!(result, Err(super::Error::<TestConfig>::InsufficientBalance.into()));
+		let call = super::Call::<TestConfig>::TransferAll { to: bob.clone(), keep_alive: false };
+		assert_eq!(balances.dispatch(RawOrigin::Signed(alice.clone()), call), Ok(().into()));
+		assert_eq!(balances.balance(&alice), 10);
+		assert_eq!(balances.balance(&bob), 130);
+
+		assert_eq!(balances.unreserve(&alice, 20), 0);
+		// Slashing destroys the funds.
+		assert_eq!(balances.slash_reserved(&alice, 30), 0);
+		assert_eq!(balances.issuance(), 170);
+		// Moving more than is reserved returns the rest.
+		assert_eq!(balances.repatriate_reserved(&alice, &bob, 20), Ok(10));
+		assert_eq!(balances.reserved_balance(&alice), 0);
+		assert_eq!(balances.reserved_balance(&bob), 10);
+		assert_eq!(balances.unreserve(&bob, 15), 5);
+		assert_eq!(balances.balance(&bob), 140);
+		assert_eq!(balances.issuance(), 170);
+		balances.assert_total_issuance();
+
+		// Reserves can only be repatriated to existing accounts.
+		let charlie = "charlie".to_string();
+		let result = balances.repatriate_reserved(&alice, &charlie, 10);
+		assert_eq!(result, Err(super::Error::<TestConfig>::DeadAccount.into()));
+
+		assert_eq!(
+			balances.take_events(),
+			vec![
+				super::Event::Reserved { who: alice.clone(), amount: 60 },
+				super::Event::Transfer { from: alice.clone(), to: bob.clone(), amount: 30 },
+				super::Event::Unreserved { who: alice.clone(), amount: 20 },
+				super::Event::ReserveRepatriated { from: alice, to: bob.clone(), amount: 10 },
+				super::Event::Unreserved { who: bob, amount: 10 },
+			]
+		);
+	}
+
+	#[test]
+	fn accounts_with_reserves_are_kept_alive() {
+		let mut balances = super::BalancesPallet::<TestConfig>::new(Storage::new());
+		let alice = "alice".to_string();
+		balances.set_balance(&alice, 105).unwrap();
+		assert_eq!(balances.reserve(&alice, 5), Ok(()));
+
+		// Reaping alice would destroy her reserved funds.
+		let call = super::Call::<TestConfig>::Transfer { to: "bob".to_string(), amount: 95 };
+		let result = balances.dispatch(RawOrigin::Signed(alice.clone()), call);
+		assert_eq!(result, Err(super::Error::<TestConfig>::KeepAlive.into()));
+		assert_eq!(
+			balances.withdraw(&alice, 95, ExistenceRequirement::AllowDeath),
+			Err(super::Error::<TestConfig>::KeepAlive.into())
+		);
+
+		// Transferring everything keeps the existential deposit.
+		let call =
+			super::Call::<TestConfig>::TransferAll { to: "bob".to_string(), keep_alive: false };
+		assert_eq!(balances.dispatch(RawOrigin::Signed(alice.clone()), call), Ok(().into()));
+		assert_eq!(balances.balance(&alice), 10);
+		assert_eq!(balances.balance(&"bob".to_string()), 90);
+		assert_eq!(balances.reserved_balance(&alice), 5);
+	}
+
+	#[test]
+	fn currency_trait() {
+		/// Pay `fee` from `who` to `to`, knowing nothing about the currency but its trait.
+		fn pay<C: Currency<String, Balance = u128>>(currency: &mut C, who: &String, to: &String) {
+			assert_eq!(currency.transfer(who, to, 50, ExistenceRequirement::KeepAlive), Ok(()));
+		}
+
+		let mut balances = super::BalancesPallet::<TestConfig>::new(Storage::new());
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		assert_eq!(
+			balances.deposit_creating(&alice, 5),
+			Err(super::Error::<TestConfig>::ExistentialDeposit.into())
+		);
+		assert_eq!(balances.deposit_creating(&alice, 100), Ok(()));
+		pay(&mut balances, &alice, &bob);
+		assert_eq!(balances.balance(&alice), 50);
+		assert_eq!(balances.balance(&bob), 50);
+
+		assert_eq!(
+			balances.withdraw(&bob, 45, ExistenceRequirement::KeepAlive),
+			Err(super::Error::<TestConfig>::KeepAlive.into())
+		);
+		assert_eq!(balances.withdraw(&bob, 20, ExistenceRequirement::KeepAlive), Ok(()));
+		assert_eq!(balances.issuance(), 80);
+		balances.assert_total_issuance();
+	}
 }
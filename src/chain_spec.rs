@@ -0,0 +1,177 @@
+//! Chain specs, describing how a chain starts.
+//!
+//! A chain spec names a chain and holds its genesis configuration, so chains with different
+//! initial states can be started without recompiling. Chain specs are written in JSON, e.g.:
+//!
+//! ```json
+//! {
+//!     "name": "Development",
+//!     "id": "dev",
+//!     "genesis": {
+//!         "system": { "blockNumber": 0 },
+//!         "balances": { "balances": [["Alice", 1000000000]] },
+//!         "proofOfExistence": { "claims": [["Hello, world!", "0x1234...cdef"]] },
+//!         "sudo": { "key": "Alice" }
+//!     }
+//! }
+//! ```
+//!
+//! Accounts are given either as the name of a development account, or as hex. Any pallet, or
+//! field, left out takes its default value.
+
+use crate::types::GenesisConfig;
+use core::fmt::Display;
+use std::{fs, io, path::Path};
+
+/// The chain specs built into the node, which can be loaded by their id.
+const BUILT_IN: [&str; 3] = [
+	include_str!("../chain-specs/dev.json"),
+	include_str!("../chain-specs/local.json"),
+	include_str!("../chain-specs/staging.json"),
+];
+
+/// The description of a chain.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainSpec {
+	/// The human readable name of the chain.
+	pub name: String,
+	/// The id of the chain, naming the directory its database is kept in.
+	pub id: String,
+	/// The genesis configuration, setting up the state the chain starts from.
+	#[serde(default)]
+	pub genesis: GenesisConfig,
+}
+
+/// Why a chain spec could not be loaded.
+#[derive(Debug)]
+pub enum Error {
+	Io(io::Error),
+	/// The chain spec is not valid JSON, or does not describe a chain.
+	Json(serde_json::Error),
+}
+
+impl From<io::Error> for Error {
+	fn from(error: io::Error) -> Self {
+		Self::Io(error)
+	}
+}
+
+impl From<serde_json::Error> for Error {
+	fn from(error: serde_json::Error) -> Self {
+		Self::Json(error)
+	}
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::Io(error) => write!(f, "{error}"),
+			Self::Json(error) => write!(f, "invalid chain spec: {error}"),
+		}
+	}
+}
+
+impl ChainSpec {
+	pub fn from_json(json: &str) -> Result<Self, Error> {
+		Ok(serde_json::from_str(json)?)
+	}
+
+	pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+		Self::from_json(&fs::read_to_string(path)?)
+	}
+
+	/// Load the built-in chain spec with the id `chain`, e.g. `dev`, `local` or `staging`, or else
+	/// the chain spec in the file at the path `chain`.
+	pub fn load(chain: &str) -> Result<Self, Error> {
+		for json in BUILT_IN {
+			let spec = Self::from_json(json)?;
+			if spec.id == chain {
+				return Ok(spec);
+			}
+		}
+		Self::from_file(chain)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ChainSpec, Error};
+	use crate::{
+		crypto::{AccountId32, Keyring},
+		node::Node,
+		proof_of_existence::Claim,
+		types::Runtime,
+	};
+
+	#[test]
+	fn built_in_chain_specs() {
+		let dev = ChainSpec::load("dev").unwrap();
+		assert_eq!(dev.name, "Development");
+		let runtime = Runtime::from_genesis(&dev.genesis).unwrap();
+		assert_eq!(runtime.system.block_number(), 0);
+		assert_eq!(runtime.balances.balance(&Keyring::Alice.to_account_id()), 1_000_000_000);
+		assert_eq!(runtime.balances.balance(&Keyring::Bob.to_account_id()), 0);
+
+		let staging = ChainSpec::load("staging").unwrap();
+		let runtime = Runtime::from_genesis(&staging.genesis).unwrap();
+		assert_eq!(runtime.system.block_number(), 1000);
+		assert_eq!(runtime.balances.balance(&Keyring::Bob.to_account_id()), 1_000_000_000_000);
+		// Genesis claims are made in the genesis block.
+		let claim = runtime.proof_of_existence.claim_of_bytes(b"Staging genesis");
+		let owner = Keyring::Charlie.to_account_id();
+		let expected = Claim { owner, block_number: 1000, description: None, expires_at: None };
+		assert_eq!(claim, Some(expected));
+		assert_eq!(runtime.sudo.key().get(), Some(Keyring::Bob.to_account_id()));
+
+		// Chains with different genesis states have different genesis blocks.
+		let local = ChainSpec::load("local").unwrap();
+		let genesis_hashes = [dev, local, staging]
+			.map(|spec| Runtime::from_genesis(&spec.genesis).unwrap().system.genesis_hash());
+		assert_ne!(genesis_hashes[0], genesis_hashes[1]);
+		assert_ne!(genesis_hashes[1], genesis_hashes[2]);
+
+		assert!(matches!(ChainSpec::load("missing"), Err(Error::Io(_))));
+	}
+
+	#[test]
+	fn chain_specs_from_json() {
+		let json = r#"{
+			"name": "Test",
+			"id": "test",
+			"genesis": {
+				"system": { "blockNumber": 5 },
+				"balances": {
+					"balances": [
+						["Alice", 10000],
+						["0x0101010101010101010101010101010101010101010101010101010101010101", 20000]
+					]
+				}
+			}
+		}"#;
+		let spec = ChainSpec::from_json(json).unwrap();
+		let runtime = Runtime::from_genesis(&spec.genesis).unwrap();
+		assert_eq!(runtime.system.block_number(), 5);
+		assert_eq!(runtime.balances.balance(&Keyring::Alice.to_account_id()), 10_000);
+		assert_eq!(runtime.balances.balance(&AccountId32([1; 32])), 20_000);
+		assert_eq!(runtime.proof_of_existence.claim_of_bytes(b"Hello, world!"), None);
+
+		// The genesis configuration may be left out, starting from the default state.
+		let spec = ChainSpec::from_json(r#"{ "name": "Empty", "id": "empty" }"#).unwrap();
+		let dir = std::env::temp_dir().join(format!("chain-spec-{}", std::process::id()));
+		let node = Node::open(&dir, &spec.genesis).unwrap();
+		assert_eq!(node.runtime.system.genesis_hash(), Runtime::new().system.genesis_hash());
+		std::fs::remove_dir_all(dir).unwrap();
+
+		// Unknown pallets and fields, and invalid accounts, are rejected.
+		for genesis in [
+			r#"{ "unknown": {} }"#,
+			r#"{ "system": { "number": 5 } }"#,
+			r#"{ "balances": { "balances": [["Dave", 10]] } }"#,
+			r#"{ "balances": { "balances": [["0x01", 10]] } }"#,
+		] {
+			let json = format!(r#"{{ "name": "Test", "id": "test", "genesis": {genesis} }}"#);
+			assert!(matches!(ChainSpec::from_json(&json), Err(Error::Json(_))));
+		}
+	}
+}
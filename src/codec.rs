@@ -0,0 +1,410 @@
+//! A minimal implementation of the SCALE codec used by Polkadot SDK chains.
+//!
+//! - Fixed-width integers are encoded in little-endian.
+//! - Lengths are encoded as compact integers, followed by the items.
+//! - Structs and tuples are encoded as the concatenation of their fields.
+//! - Enums are encoded as the index of the variant as a `u8`, followed by its fields.
+//!
+//! Structs and enums can derive the codec with `#[derive(macros::Encode, macros::Decode)]`.
+//! Decoding is strict: any input which is not exactly what `Encode` produces for some value is
+//! rejected, so every value has a single encoding.
+
+use core::{cell::Cell, fmt::Display};
+
+/// A type which can be encoded into bytes.
+pub trait Encode {
+	/// Append the encoding of `self` to `dest`.
+	fn encode_to(&self, dest: &mut Vec<u8>);
+
+	/// Encode `self` into a new vector of bytes.
+	fn encode(&self) -> Vec<u8> {
+		let mut dest = Vec::new();
+		self.encode_to(&mut dest);
+		dest
+	}
+}
+
+/// An error while decoding a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The input ended before the value was fully decoded.
+	UnexpectedEnd,
+	/// The input is not the encoding of any value of the type.
+	Invalid(&'static str),
+	/// The value was decoded, but the input has bytes left over.
+	TrailingBytes,
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+			Self::Invalid(reason) => write!(f, "{reason}"),
+			Self::TrailingBytes => write!(f, "trailing bytes after the value"),
+		}
+	}
+}
+
+/// A type which can be decoded from bytes.
+pub trait Decode: Sized {
+	/// Decode a value from the start of `input`, advancing it past the consumed bytes.
+	fn decode(input: &mut &[u8]) -> Result<Self, Error>;
+
+	/// Decode a value from `input`, which must contain nothing else.
+	fn decode_all(mut input: &[u8]) -> Result<Self, Error> {
+		let value = Self::decode(&mut input)?;
+		if !input.is_empty() {
+			return Err(Error::TrailingBytes);
+		}
+		Ok(value)
+	}
+}
+
+/// Take the next `len` bytes from `input`.
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], Error> {
+	if input.len() < len {
+		return Err(Error::UnexpectedEnd);
+	}
+	let (bytes, rest) = input.split_at(len);
+	*input = rest;
+	Ok(bytes)
+}
+
+/// A wrapper for integers which should use the compact encoding.
+///
+/// Small values take less space: values below `2^6` use 1 byte, below `2^14` use 2 bytes, below
+/// `2^30` use 4 bytes, and bigger values use a length byte followed by the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compact<T>(pub T);
+
+impl Encode for Compact<u128> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		let value = self.0;
+		match value {
+			0..=0x3f => dest.push((value as u8) << 2),
+			0x40..=0x3fff => dest.extend_from_slice(&(((value as u16) << 2) | 0b01).to_le_bytes()),
+			0x4000..=0x3fff_ffff => {
+				dest.extend_from_slice(&(((value as u32) << 2) | 0b10).to_le_bytes())
+			},
+			_ => {
+				let bytes = value.to_le_bytes();
+				let len = bytes.iter().rposition(|byte| *byte != 0).map_or(0, |i| i + 1).max(4);
+				dest.push((((len - 4) as u8) << 2) | 0b11);
+				dest.extend_from_slice(&bytes[..len]);
+			},
+		}
+	}
+}
+
+impl Decode for Compact<u128> {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		let prefix = u8::decode(input)?;
+		// Each mode must be used only for values which do not fit the smaller ones.
+		let (value, min) = match prefix & 0b11 {
+			0b00 => return Ok(Compact((prefix >> 2) as u128)),
+			0b01 => {
+				let bytes = [prefix, u8::decode(input)?];
+				((u16::from_le_bytes(bytes) >> 2) as u128, 0x40)
+			},
+			0b10 => {
+				let mut bytes = [prefix, 0, 0, 0];
+				bytes[1..].copy_from_slice(take(input, 3)?);
+				((u32::from_le_bytes(bytes) >> 2) as u128, 0x4000)
+			},
+			_ => {
+				let len = (prefix >> 2) as usize + 4;
+				if len > 16 {
+					return Err(Error::Invalid("compact integer out of range"));
+				}
+				let mut bytes = [0u8; 16];
+				bytes[..len].copy_from_slice(take(input, len)?);
+				let value = u128::from_le_bytes(bytes);
+				let min = if len == 4 { 0x4000_0000 } else { 1u128 << ((len - 1) * 8) };
+				(value, min)
+			},
+		};
+		if value < min {
+			return Err(Error::Invalid("compact integer is not canonical"));
+		}
+		Ok(Compact(value))
+	}
+}
+
+macro_rules! impl_compact {
+	($( $t:ty ),*) => {
+		$(
+			impl Encode for Compact<$t> {
+				fn encode_to(&self, dest: &mut Vec<u8>) {
+					Compact(self.0 as u128).encode_to(dest)
+				}
+			}
+
+			impl Decode for Compact<$t> {
+				fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+					let value = Compact::<u128>::decode(input)?.0;
+					<$t>::try_from(value)
+						.map(Compact)
+						.map_err(|_| Error::Invalid("compact integer out of range"))
+				}
+			}
+		)*
+	};
+}
+
+impl_compact!(u8, u16, u32, u64);
+
+macro_rules! impl_integer {
+	($( $t:ty ),*) => {
+		$(
+			impl Encode for $t {
+				fn encode_to(&self, dest: &mut Vec<u8>) {
+					dest.extend_from_slice(&self.to_le_bytes())
+				}
+			}
+
+			impl Decode for $t {
+				fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+					let bytes = take(input, core::mem::size_of::<$t>())?;
+					Ok(<$t>::from_le_bytes(bytes.try_into().expect("took the size of the integer")))
+				}
+			}
+		)*
+	};
+}
+
+impl_integer!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Encode for bool {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.push(*self as u8)
+	}
+}
+
+impl Decode for bool {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		match u8::decode(input)? {
+			0 => Ok(false),
+			1 => Ok(true),
+			_ => Err(Error::Invalid("invalid boolean")),
+		}
+	}
+}
+
+impl<const N: usize> Encode for [u8; N] {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.extend_from_slice(self)
+	}
+}
+
+impl<const N: usize> Decode for [u8; N] {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		Ok(take(input, N)?.try_into().expect("took N bytes"))
+	}
+}
+
+impl<T: Encode> Encode for [T] {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		Compact(self.len() as u64).encode_to(dest);
+		for item in self {
+			item.encode_to(dest);
+		}
+	}
+}
+
+impl<T: Encode> Encode for Vec<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_slice().encode_to(dest)
+	}
+}
+
+impl<T: Decode> Decode for Vec<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		let len = Compact::<u64>::decode(input)?.0;
+		// Every item takes at least one byte, so don't trust the length to preallocate beyond that.
+		let mut items = Vec::with_capacity((len as usize).min(input.len()));
+		for _ in 0..len {
+			items.push(T::decode(input)?);
+		}
+		Ok(items)
+	}
+}
+
+impl Encode for str {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_bytes().encode_to(dest)
+	}
+}
+
+impl Encode for String {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_str().encode_to(dest)
+	}
+}
+
+impl Decode for String {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		String::from_utf8(Vec::decode(input)?).map_err(|_| Error::Invalid("invalid utf-8 string"))
+	}
+}
+
+impl<T: Encode> Encode for Option<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			None => dest.push(0),
+			Some(value) => {
+				dest.push(1);
+				value.encode_to(dest);
+			},
+		}
+	}
+}
+
+impl<T: Decode> Decode for Option<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		match u8::decode(input)? {
+			0 => Ok(None),
+			1 => Ok(Some(T::decode(input)?)),
+			_ => Err(Error::Invalid("invalid option variant")),
+		}
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(**self).encode_to(dest)
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for Box<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(**self).encode_to(dest)
+	}
+}
+
+impl<T: Decode> Decode for Box<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		T::decode(input).map(Box::new)
+	}
+}
+
+/// The unit type encodes to nothing, e.g. for storage items whose keys are all that matters.
+impl Encode for () {
+	fn encode_to(&self, _dest: &mut Vec<u8>) {}
+}
+
+impl Decode for () {
+	fn decode(_input: &mut &[u8]) -> Result<Self, Error> {
+		Ok(())
+	}
+}
+
+macro_rules! impl_tuple {
+	($( $t:ident ),*) => {
+		#[allow(non_snake_case)]
+		impl<$( $t: Encode ),*> Encode for ($( $t, )*) {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				let ($( $t, )*) = self;
+				$( $t.encode_to(dest); )*
+			}
+		}
+
+		impl<$( $t: Decode ),*> Decode for ($( $t, )*) {
+			fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+				Ok(($( $t::decode(input)?, )*))
+			}
+		}
+	};
+}
+
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+
+thread_local! {
+	/// How many values decoded with `decode_with_depth_limit` are nested around the one being
+	/// decoded on this thread.
+	static DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Decode a value of a type which can contain itself, e.g. a call dispatching other calls, failing
+/// once more than `limit` of them are nested inside each other rather than overflowing the stack.
+///
+/// Every value of the type must be decoded through this, so the nesting is counted at every level.
+pub fn decode_with_depth_limit<T>(
+	limit: u32,
+	input: &mut &[u8],
+	decode: impl FnOnce(&mut &[u8]) -> Result<T, Error>,
+) -> Result<T, Error> {
+	let depth = DEPTH.get();
+	if depth >= limit {
+		return Err(Error::Invalid("value nested too deeply"));
+	}
+	DEPTH.set(depth + 1);
+	let result = decode(input);
+	DEPTH.set(depth);
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Compact, Decode, Encode, Error};
+
+	#[test]
+	fn compact_integers() {
+		assert_eq!(Compact(0u32).encode(), vec![0x00]);
+		assert_eq!(Compact(63u32).encode(), vec![0xfc]);
+		assert_eq!(Compact(64u32).encode(), vec![0x01, 0x01]);
+		assert_eq!(Compact(16383u32).encode(), vec![0xfd, 0xff]);
+		assert_eq!(Compact(16384u32).encode(), vec![0x02, 0x00, 0x01, 0x00]);
+		assert_eq!(Compact(1u64 << 30).encode(), vec![0x03, 0x00, 0x00, 0x00, 0x40]);
+		assert_eq!(
+			Compact(u64::MAX).encode(),
+			vec![0x13, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+		);
+	}
+
+	#[test]
+	fn collections_and_tuples() {
+		assert_eq!(42u32.encode(), vec![42, 0, 0, 0]);
+		assert_eq!("abc".encode(), vec![12, b'a', b'b', b'c']);
+		assert_eq!(vec![1u16, 2].encode(), vec![8, 1, 0, 2, 0]);
+		assert_eq!(Some(true).encode(), vec![1, 1]);
+		assert_eq!(None::<u8>.encode(), vec![0]);
+		assert_eq!((1u8, [2u8; 2], "c").encode(), vec![1, 2, 2, 4, b'c']);
+		assert_eq!(().encode(), Vec::<u8>::new());
+	}
+
+	#[test]
+	fn round_trip() {
+		fn assert_round_trip<T: Encode + Decode + PartialEq + core::fmt::Debug>(value: T) {
+			assert_eq!(T::decode_all(&value.encode()), Ok(value));
+		}
+
+		for value in [0, 63, 64, 16383, 16384, 1 << 30, (1 << 32) - 1, u64::MAX] {
+			assert_round_trip(Compact(value));
+		}
+		assert_round_trip(Compact(u128::MAX));
+		assert_round_trip(-42i64);
+		assert_round_trip(vec![String::from("abc"), String::new()]);
+		assert_round_trip((true, Some(7u16), None::<u8>, [9u8; 3]));
+		assert_round_trip(Box::new(vec![Some(Compact(1u32))]));
+	}
+
+	#[test]
+	fn rejects_invalid_input() {
+		assert_eq!(u32::decode_all(&[1, 2, 3]), Err(Error::UnexpectedEnd));
+		assert_eq!(u8::decode_all(&[1, 2]), Err(Error::TrailingBytes));
+		assert!(matches!(bool::decode_all(&[2]), Err(Error::Invalid(_))));
+		assert!(matches!(String::decode_all(&[4, 0xff]), Err(Error::Invalid(_))));
+		// A length prefix promising more items than there are.
+		assert_eq!(Vec::<u8>::decode_all(&[0xfc]), Err(Error::UnexpectedEnd));
+		// Compact integers must use the shortest encoding.
+		assert!(matches!(Compact::<u32>::decode_all(&[0x01, 0x00]), Err(Error::Invalid(_))));
+		assert!(matches!(
+			Compact::<u32>::decode_all(&[0x07, 0, 0, 0, 0, 1]),
+			Err(Error::Invalid(_))
+		));
+		assert!(matches!(Compact::<u8>::decode_all(&[0x01, 0x04]), Err(Error::Invalid(_))));
+	}
+}
@@ -0,0 +1,304 @@
+//! Hashing, accounts and signatures.
+//!
+//! Accounts are identified by a 32 byte public key, which can either be an sr25519 or an ed25519
+//! key. Signatures carry their scheme, so they can be verified against the account directly.
+
+use blake2::{Blake2b, Digest, digest::consts::U32};
+use core::{fmt::Debug, str::FromStr};
+
+/// A 256 bit hash.
+pub type H256 = [u8; 32];
+
+/// The signing context used for sr25519 signatures, the same as used by Substrate.
+const SR25519_CONTEXT: &[u8] = b"substrate";
+
+/// Hash `data` with Blake2b, producing a 256 bit hash.
+pub fn blake2_256(data: &[u8]) -> H256 {
+	Blake2b::<U32>::digest(data).into()
+}
+
+/// A hash function, e.g. to identify data by its hash.
+pub trait Hasher {
+	/// The hash of some data.
+	type Output;
+
+	fn hash(data: &[u8]) -> Self::Output;
+}
+
+/// Blake2b, producing a 256 bit hash. Also used for block and state hashes.
+pub struct BlakeTwo256;
+
+impl Hasher for BlakeTwo256 {
+	type Output = H256;
+
+	fn hash(data: &[u8]) -> H256 {
+		blake2_256(data)
+	}
+}
+
+/// The root of a binary merkle tree over `leaves`.
+///
+/// Each node is the hash of the concatenation of its two children, and a node without a sibling is
+/// moved up a level unchanged. The root of no leaves is the hash of empty data.
+pub fn merkle_root(leaves: impl IntoIterator<Item = H256>) -> H256 {
+	let mut layer = leaves.into_iter().collect::<Vec<_>>();
+	if layer.is_empty() {
+		return blake2_256(&[]);
+	}
+	while layer.len() > 1 {
+		layer = layer
+			.chunks(2)
+			.map(|pair| match pair {
+				[left, right] => blake2_256(&[*left, *right].concat()),
+				_ => pair[0],
+			})
+			.collect();
+	}
+	layer[0]
+}
+
+/// An account, identified by its 32 byte public key.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, macros::Encode, macros::Decode)]
+pub struct AccountId32(pub [u8; 32]);
+
+impl Debug for AccountId32 {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match Keyring::from_account_id(self) {
+			Some(keyring) => write!(f, "{keyring:?}"),
+			None => {
+				write!(f, "0x{}", self.0.iter().map(|b| format!("{b:02x}")).collect::<String>())
+			},
+		}
+	}
+}
+
+/// Accounts are written either as the name of a development account, e.g. `Alice`, or as their
+/// public key in hex, e.g. `0x0d1c...`.
+impl FromStr for AccountId32 {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Some(keyring) = Keyring::ALL.into_iter().find(|keyring| format!("{keyring:?}") == s)
+		{
+			return Ok(keyring.to_account_id());
+		}
+		let hex = s
+			.strip_prefix("0x")
+			.ok_or("expected a development account or 0x-prefixed hex")?;
+		if hex.len() != 64 || !hex.is_ascii() {
+			return Err("expected 32 bytes of hex");
+		}
+		let mut account = [0; 32];
+		for (byte, digits) in account.iter_mut().zip(hex.as_bytes().chunks(2)) {
+			let digits = core::str::from_utf8(digits).expect("hex is ascii");
+			*byte = u8::from_str_radix(digits, 16).map_err(|_| "invalid hex")?;
+		}
+		Ok(Self(account))
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for AccountId32 {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let s = String::deserialize(deserializer)?;
+		s.parse().map_err(serde::de::Error::custom)
+	}
+}
+
+/// A signature made with either of the supported schemes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum MultiSignature {
+	Ed25519([u8; 64]),
+	Sr25519([u8; 64]),
+}
+
+/// A signature which can be verified against the account which made it.
+pub trait Verify {
+	/// The type identifying the signer.
+	type Signer;
+
+	/// Check that `self` is a valid signature of `message` by `signer`.
+	fn verify(&self, message: &[u8], signer: &Self::Signer) -> bool;
+}
+
+impl Verify for MultiSignature {
+	type Signer = AccountId32;
+
+	fn verify(&self, message: &[u8], signer: &AccountId32) -> bool {
+		match self {
+			Self::Ed25519(signature) => {
+				let Ok(public) = ed25519_dalek::VerifyingKey::from_bytes(&signer.0) else {
+					return false;
+				};
+				let signature = ed25519_dalek::Signature::from_bytes(signature);
+				public.verify_strict(message, &signature).is_ok()
+			},
+			Self::Sr25519(signature) => {
+				let Ok(public) = schnorrkel::PublicKey::from_bytes(&signer.0) else { return false };
+				let Ok(signature) = schnorrkel::Signature::from_bytes(signature) else {
+					return false;
+				};
+				public.verify_simple(SR25519_CONTEXT, message, &signature).is_ok()
+			},
+		}
+	}
+}
+
+/// A key pair, which can sign messages on behalf of its account.
+pub enum Pair {
+	Ed25519(ed25519_dalek::SigningKey),
+	Sr25519(schnorrkel::Keypair),
+}
+
+impl Pair {
+	/// Create an ed25519 key pair from a secret seed.
+	pub fn ed25519_from_seed(seed: &[u8; 32]) -> Self {
+		Self::Ed25519(ed25519_dalek::SigningKey::from_bytes(seed))
+	}
+
+	/// Create an sr25519 key pair from a secret seed.
+	pub fn sr25519_from_seed(seed: &[u8; 32]) -> Self {
+		let secret = schnorrkel::MiniSecretKey::from_bytes(seed).expect("seed is 32 bytes");
+		Self::Sr25519(secret.expand_to_keypair(schnorrkel::ExpansionMode::Ed25519))
+	}
+
+	/// The account of this key pair.
+	pub fn account_id(&self) -> AccountId32 {
+		match self {
+			Self::Ed25519(pair) => AccountId32(pair.verifying_key().to_bytes()),
+			Self::Sr25519(pair) => AccountId32(pair.public.to_bytes()),
+		}
+	}
+
+	/// Sign `message` with this key pair.
+	pub fn sign(&self, message: &[u8]) -> MultiSignature {
+		match self {
+			Self::Ed25519(pair) => {
+				MultiSignature::Ed25519(ed25519_dalek::Signer::sign(pair, message).to_bytes())
+			},
+			Self::Sr25519(pair) => {
+				MultiSignature::Sr25519(pair.sign_simple(SR25519_CONTEXT, message).to_bytes())
+			},
+		}
+	}
+}
+
+/// Well-known development accounts.
+///
+/// Their secret seeds are public, so they must only ever be used for testing. Note that these are
+/// not the same keys as the Substrate development accounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keyring {
+	Alice,
+	Bob,
+	Charlie,
+}
+
+impl Keyring {
+	/// All the development accounts.
+	pub const ALL: [Keyring; 3] = [Keyring::Alice, Keyring::Bob, Keyring::Charlie];
+
+	/// The secret seed of the account, derived from its name.
+	fn seed(self) -> [u8; 32] {
+		blake2_256(format!("//{self:?}").as_bytes())
+	}
+
+	/// The sr25519 key pair of the account.
+	pub fn pair(self) -> Pair {
+		Pair::sr25519_from_seed(&self.seed())
+	}
+
+	/// The ed25519 key pair of the account. Its account is different from the sr25519 one.
+	pub fn ed25519_pair(self) -> Pair {
+		Pair::ed25519_from_seed(&self.seed())
+	}
+
+	/// The sr25519 account.
+	pub fn to_account_id(self) -> AccountId32 {
+		self.pair().account_id()
+	}
+
+	/// Find the development account with the given sr25519 account id.
+	pub fn from_account_id(who: &AccountId32) -> Option<Self> {
+		Self::ALL.into_iter().find(|keyring| keyring.to_account_id() == *who)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		AccountId32, BlakeTwo256, H256, Hasher, Keyring, MultiSignature, Verify, blake2_256,
+		merkle_root,
+	};
+	use sha2::Digest;
+
+	/// SHA-256, e.g. to match the hashes given by `sha256sum`.
+	struct Sha256;
+
+	impl Hasher for Sha256 {
+		type Output = H256;
+
+		fn hash(data: &[u8]) -> H256 {
+			sha2::Sha256::digest(data).into()
+		}
+	}
+
+	#[test]
+	fn sign_and_verify() {
+		let message = b"Hello, world!";
+		for pair in [Keyring::Alice.pair(), Keyring::Alice.ed25519_pair()] {
+			let signature = pair.sign(message);
+			assert!(signature.verify(message, &pair.account_id()));
+			assert!(!signature.verify(b"Goodbye, world!", &pair.account_id()));
+			assert!(!signature.verify(message, &Keyring::Bob.to_account_id()));
+		}
+
+		// A valid signature does not verify with the wrong scheme.
+		let MultiSignature::Sr25519(signature) = Keyring::Alice.pair().sign(message) else {
+			panic!("expected an sr25519 signature")
+		};
+		let signature = MultiSignature::Ed25519(signature);
+		assert!(!signature.verify(message, &Keyring::Alice.to_account_id()));
+	}
+
+	#[test]
+	fn keyring_accounts_are_distinct() {
+		assert_ne!(Keyring::Alice.to_account_id(), Keyring::Bob.to_account_id());
+		assert_ne!(Keyring::Alice.to_account_id(), Keyring::Alice.ed25519_pair().account_id());
+		assert_eq!(
+			Keyring::from_account_id(&Keyring::Charlie.to_account_id()),
+			Some(Keyring::Charlie)
+		);
+	}
+
+	#[test]
+	fn accounts_from_str() {
+		assert_eq!("Alice".parse(), Ok(Keyring::Alice.to_account_id()));
+		// Accounts which are not development accounts are written in hex.
+		let bob = Keyring::Bob.ed25519_pair().account_id();
+		assert_eq!(format!("{bob:?}").parse(), Ok(bob));
+		assert!("Dave".parse::<AccountId32>().is_err());
+		assert!("0x00".parse::<AccountId32>().is_err());
+		assert!(format!("0x{}", "zz".repeat(32)).parse::<AccountId32>().is_err());
+	}
+
+	#[test]
+	fn hashers() {
+		assert_eq!(BlakeTwo256::hash(b"abc"), blake2_256(b"abc"));
+		// The SHA-256 test vector from FIPS 180-2.
+		let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+		let hex = Sha256::hash(b"abc").map(|byte| format!("{byte:02x}")).concat();
+		assert_eq!(hex, sha256);
+	}
+
+	#[test]
+	fn merkle_roots() {
+		let [a, b, c] = [b"a", b"b", b"c"].map(|data| blake2_256(data));
+		let node = |left: [u8; 32], right: [u8; 32]| blake2_256(&[left, right].concat());
+
+		assert_eq!(merkle_root([]), blake2_256(&[]));
+		assert_eq!(merkle_root([a]), a);
+		assert_eq!(merkle_root([a, b]), node(a, b));
+		assert_eq!(merkle_root([a, b, c]), node(node(a, b), c));
+		assert_ne!(merkle_root([b, a]), merkle_root([a, b]));
+	}
+}
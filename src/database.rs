@@ -0,0 +1,290 @@
+//! The database of a node, persisting the chain across restarts.
+//!
+//! The database is a directory holding two files:
+//! - `blocks`: every imported block in order, encoded and prefixed with its length as a `u32`.
+//!   Blocks are only ever appended, and a block which was only partly written when the node
+//!   stopped is dropped when the database is opened again.
+//! - `state`: the storage of the runtime after the block it was written for. It is replaced as a
+//!   whole, by writing a new file and renaming it over the old one, so it is never half written.
+//!
+//! Blocks are written before the state, so the state is never ahead of the blocks. If the node
+//! stops in between, it catches up by executing the missing blocks again, see `crate::node`.
+
+use crate::{
+	codec::{self, Decode, Encode},
+	crypto::H256,
+	types::types::{Block, BlockNumber, Header},
+};
+use core::fmt::Display;
+use std::{
+	collections::BTreeMap,
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+
+/// The file holding the blocks, in the database directory.
+const BLOCKS: &str = "blocks";
+/// The file holding the state, in the database directory.
+const STATE: &str = "state";
+
+/// The storage of the runtime after a block.
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct State {
+	/// The number of the block the state was written for, or of the genesis block for the genesis
+	/// state.
+	pub block_number: BlockNumber,
+	/// Every entry of the storage, with its raw key and value.
+	pub entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Why the database could not be read or written.
+#[derive(Debug)]
+pub enum Error {
+	Io(io::Error),
+	/// The database holds data which can not be decoded.
+	Codec(codec::Error),
+	/// The blocks in the database are not numbered in order.
+	UnorderedBlocks,
+}
+
+impl From<io::Error> for Error {
+	fn from(error: io::Error) -> Self {
+		Self::Io(error)
+	}
+}
+
+impl From<codec::Error> for Error {
+	fn from(error: codec::Error) -> Self {
+		Self::Codec(error)
+	}
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::Io(error) => write!(f, "{error}"),
+			Self::Codec(error) => write!(f, "invalid encoding: {error}"),
+			Self::UnorderedBlocks => write!(f, "blocks are not numbered in order"),
+		}
+	}
+}
+
+/// A database of blocks and state, stored in a directory.
+///
+/// The blocks are also kept in memory, indexed by number and by hash.
+pub struct Database {
+	dir: PathBuf,
+	/// The blocks file, opened for appending.
+	blocks_file: File,
+	/// Every block, in order. Their numbers follow each other, starting right after the genesis
+	/// block.
+	blocks: Vec<Block>,
+	/// The number of every block, by the hash of its header.
+	numbers: BTreeMap<H256, BlockNumber>,
+}
+
+impl Database {
+	/// Open the database in `dir`, creating it if needed.
+	pub fn open(dir: impl AsRef<Path>) -> Result<Self, Error> {
+		let dir = dir.as_ref().to_path_buf();
+		fs::create_dir_all(&dir)?;
+
+		// Read the blocks until the end of the file, or until a block which was not fully written.
+		let bytes = match fs::read(dir.join(BLOCKS)) {
+			Ok(bytes) => bytes,
+			Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+			Err(error) => return Err(error.into()),
+		};
+		let mut blocks = Vec::new();
+		let mut input = &bytes[..];
+		while let Some((record, rest)) = read_record(input) {
+			blocks.push(Block::decode_all(record)?);
+			input = rest;
+		}
+
+		// Drop any partly written block, so new blocks are appended right after the last full one.
+		let blocks_file = OpenOptions::new().create(true).append(true).open(dir.join(BLOCKS))?;
+		blocks_file.set_len((bytes.len() - input.len()) as u64)?;
+
+		let mut numbers = BTreeMap::new();
+		for (i, block) in blocks.iter().enumerate() {
+			if i > 0 && block.header.block_number != blocks[i - 1].header.block_number + 1 {
+				return Err(Error::UnorderedBlocks);
+			}
+			numbers.insert(block.header.hash(), block.header.block_number);
+		}
+
+		Ok(Self { dir, blocks_file, blocks, numbers })
+	}
+
+	/// Every block, in order.
+	pub fn blocks(&self) -> &[Block] {
+		&self.blocks
+	}
+
+	/// The number of the latest block, if there are any blocks yet.
+	pub fn best_number(&self) -> Option<BlockNumber> {
+		Some(self.blocks.last()?.header.block_number)
+	}
+
+	pub fn block(&self, block_number: BlockNumber) -> Option<&Block> {
+		let first = self.blocks.first()?.header.block_number;
+		self.blocks.get(block_number.checked_sub(first)? as usize)
+	}
+
+	pub fn block_by_hash(&self, hash: &H256) -> Option<&Block> {
+		self.block(*self.numbers.get(hash)?)
+	}
+
+	pub fn header(&self, block_number: BlockNumber) -> Option<&Header> {
+		Some(&self.block(block_number)?.header)
+	}
+
+	pub fn header_by_hash(&self, hash: &H256) -> Option<&Header> {
+		Some(&self.block_by_hash(hash)?.header)
+	}
+
+	/// Append the next block, which must follow the latest one. The first block may have any
+	/// number, following the genesis block of the chain.
+	pub fn append_block(&mut self, block: Block) -> Result<(), Error> {
+		if let Some(best_number) = self.best_number()
+			&& block.header.block_number != best_number + 1
+		{
+			return Err(Error::UnorderedBlocks);
+		}
+		let encoded = block.encode();
+		let mut record = (encoded.len() as u32).to_le_bytes().to_vec();
+		record.extend(encoded);
+		self.blocks_file.write_all(&record)?;
+		self.blocks_file.sync_data()?;
+
+		self.numbers.insert(block.header.hash(), block.header.block_number);
+		self.blocks.push(block);
+		Ok(())
+	}
+
+	/// The latest state written, if any.
+	pub fn state(&self) -> Result<Option<State>, Error> {
+		match fs::read(self.dir.join(STATE)) {
+			Ok(bytes) => Ok(Some(State::decode_all(&bytes)?)),
+			Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+			Err(error) => Err(error.into()),
+		}
+	}
+
+	/// Replace the state.
+	pub fn store_state(&self, state: &State) -> Result<(), Error> {
+		let tmp = self.dir.join(format!("{STATE}.tmp"));
+		let mut file = File::create(&tmp)?;
+		file.write_all(&state.encode())?;
+		file.sync_all()?;
+		fs::rename(tmp, self.dir.join(STATE))?;
+		Ok(())
+	}
+}
+
+/// Split the first record, prefixed with its length, off `input`. Returns `None` if `input` does
+/// not start with a full record.
+fn read_record(input: &[u8]) -> Option<(&[u8], &[u8])> {
+	let (len, rest) = input.split_first_chunk::<4>()?;
+	let len = u32::from_le_bytes(*len) as usize;
+	(rest.len() >= len).then(|| rest.split_at(len))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{BLOCKS, Database, State};
+	use crate::{
+		support::{Block, Header, extrinsics_root},
+		types::types::UncheckedExtrinsic,
+	};
+	use std::{fs, path::PathBuf};
+
+	/// A fresh directory for the test `name`.
+	fn test_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("database-{}-{name}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		dir
+	}
+
+	/// An empty block following `parent`.
+	fn empty_block(parent: Option<&Header<u32>>) -> Block<Header<u32>, UncheckedExtrinsic> {
+		let header = Header {
+			parent_hash: parent.map(|parent| parent.hash()).unwrap_or_default(),
+			block_number: parent.map(|parent| parent.block_number + 1).unwrap_or(1),
+			state_root: Default::default(),
+			extrinsics_root: extrinsics_root::<UncheckedExtrinsic>(&[]),
+			digest: Default::default(),
+		};
+		Block { header, extrinsics: vec![] }
+	}
+
+	#[test]
+	fn blocks_and_state_survive_reopening() {
+		let dir = test_dir("reopen");
+		let mut database = Database::open(&dir).unwrap();
+		assert_eq!(database.state().unwrap(), None);
+		let block_1 = empty_block(None);
+		let block_2 = empty_block(Some(&block_1.header));
+		database.append_block(block_1.clone()).unwrap();
+		database.append_block(block_2.clone()).unwrap();
+		let state = State { block_number: 2, entries: vec![(b"key".to_vec(), b"value".to_vec())] };
+		database.store_state(&state).unwrap();
+		// Blocks must be appended in order.
+		assert!(database.append_block(block_2.clone()).is_err());
+		drop(database);
+
+		let database = Database::open(&dir).unwrap();
+		assert_eq!(database.best_number(), Some(2));
+		assert_eq!(database.header(1), Some(&block_1.header));
+		assert_eq!(database.header_by_hash(&block_2.header.hash()), Some(&block_2.header));
+		assert_eq!(database.header(3), None);
+		assert_eq!(database.state().unwrap(), Some(state));
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn blocks_start_after_the_genesis_block() {
+		let dir = test_dir("genesis-number");
+		let mut database = Database::open(&dir).unwrap();
+		assert_eq!(database.best_number(), None);
+		let mut block_10 = empty_block(None);
+		block_10.header.block_number = 10;
+		let block_11 = empty_block(Some(&block_10.header));
+		database.append_block(block_10.clone()).unwrap();
+		assert!(database.append_block(block_10.clone()).is_err());
+		database.append_block(block_11.clone()).unwrap();
+		drop(database);
+
+		let database = Database::open(&dir).unwrap();
+		assert_eq!(database.best_number(), Some(11));
+		assert_eq!(database.header(9), None);
+		assert_eq!(database.header(10), Some(&block_10.header));
+		assert_eq!(database.header(11), Some(&block_11.header));
+		assert_eq!(database.header(12), None);
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn partly_written_blocks_are_dropped() {
+		let dir = test_dir("partly-written");
+		let mut database = Database::open(&dir).unwrap();
+		let block_1 = empty_block(None);
+		database.append_block(block_1.clone()).unwrap();
+		drop(database);
+
+		// Simulate the node stopping while writing the second block.
+		let mut bytes = fs::read(dir.join(BLOCKS)).unwrap();
+		let len = bytes.len();
+		bytes.extend_from_within(..len - 3);
+		fs::write(dir.join(BLOCKS), bytes).unwrap();
+
+		let mut database = Database::open(&dir).unwrap();
+		assert_eq!(database.best_number(), Some(1));
+		database.append_block(empty_block(Some(&block_1.header))).unwrap();
+		drop(database);
+		assert_eq!(Database::open(&dir).unwrap().best_number(), Some(2));
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
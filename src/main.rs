@@ -1,139 +1,891 @@
 mod balances;
+mod chain_spec;
+mod codec;
+mod crypto;
+mod database;
+mod node;
 mod proof_of_existence;
+mod storage;
+mod sudo;
 mod support;
 mod system;
+mod transaction_payment;
+mod trie;
 mod types;
+mod utility;
 
 use crate::{
-	support::{Dispatch, DispatchResult, Extrinsic},
-	types::{BalancesPallet, Runtime, RuntimeCall, SystemConfig, SystemPallet, types::Block},
+	chain_spec::ChainSpec,
+	codec::{Decode, Encode},
+	crypto::Keyring,
+	node::Node,
+	storage::verify_proof,
+	types::{
+		Runtime, RuntimeCall,
+		types::{AccountId, Balance, Block, BlockNumber, UncheckedExtrinsic},
+	},
 };
+use std::path::PathBuf;
 
-impl Runtime {
-	fn new() -> Self {
-		Self { 
-			system: SystemPallet::new(),
-			balances: BalancesPallet::new(),
-			proof_of_existence: proof_of_existence::Pallet::new()
-		 }
+fn main() {
+    // Accounts can either use sr25519 or ed25519 keys.
+    let alice = Keyring::Alice.pair();
+    let bob = Keyring::Bob.ed25519_pair();
+    let charlie = Keyring::Charlie.to_account_id();
+
+    // The chain is described by a chain spec, given with `--chain`: either the id of a built-in
+    // chain spec (`dev`, `local` or `staging`), or the path to a JSON chain spec.
+    // The chain is kept in a database under the directory given with `--base-path`.
+    let mut chain = "dev".to_string();
+    let mut base_path = PathBuf::from("chain-db");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--chain", Some(value)) => chain = value,
+            ("--base-path", Some(value)) => base_path = PathBuf::from(value),
+            _ => panic!("usage: rust-state-machine [--chain <id or path>] [--base-path <dir>]"),
+        }
+    }
+    let spec = ChainSpec::load(&chain).unwrap_or_else(|error| panic!("{chain}: {error}"));
+    println!("Running the {} chain", spec.name);
+
+    // Open our node, which runs a new instance of the Runtime with all the modules it uses.
+    // New chains start from the genesis state of the chain spec.
+    // The node keeps its chain in a database, so every run resumes from the latest block of the
+    // previous one.
+    // A second node authors the blocks, which our node then imports.
+    let dir = base_path.join(&spec.id);
+    let mut node = Node::open(dir.join("node"), &spec.genesis).expect("failed to open the node");
+    let mut author =
+        Node::open(dir.join("author"), &spec.genesis).expect("failed to open the author");
+    let runtime = &node.runtime;
+    println!("Resuming from block {}", runtime.system.block_number());
+
+    // Extrinsics are signed at the latest block of this chain, and are only valid for a while.
+    // Nonces follow on from the extrinsics of the previous runs.
+    let birth = runtime.system.block_number();
+    let birth_hash = runtime.system.parent_hash();
+    let sign =
+        |pair, nonce, call| UncheckedExtrinsic::new_signed(pair, nonce, call, birth, &birth_hash);
+    let alice_nonce = runtime.system.nonce(&alice.account_id());
+    let bob_nonce = runtime.system.nonce(&bob.account_id());
+
+    // Content is claimed by its hash, so only the hash is stored on chain.
+    let content = b"Hello, world!";
+    let claim = proof_of_existence::Pallet::<Runtime>::claim_for_bytes(content);
+
+    // Here are the extrinsics in our blocks.
+    // You can add or remove these based on the modules and calls you have set up.
+    // Every extrinsic pays a fee for its weight and length, so its caller needs some balance.
+    let extrinsics_1 = vec![
+        sign(
+            &alice,
+            alice_nonce,
+            RuntimeCall::Balances(balances::Call::Transfer {
+                to: bob.account_id(),
+                amount: 100_000,
+            }),
+        ),
+        sign(
+            &alice,
+            alice_nonce + 1,
+            RuntimeCall::Balances(balances::Call::Transfer { to: charlie, amount: 50_000 }),
+        ),
+    ];
+
+    let extrinsics_2 = vec![
+        sign(
+            &alice,
+            alice_nonce + 2,
+            RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
+                claim,
+                description: Some(b"A friendly greeting".to_vec()),
+                expires_at: None,
+            }),
+        ),
+        sign(
+            &bob,
+            bob_nonce,
+            RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
+                claim,
+                description: None,
+                expires_at: None,
+            }),
+        ),
+    ];
+
+    let extrinsics_3 = vec![
+        sign(
+            &alice,
+            alice_nonce + 3,
+            RuntimeCall::ProofOfExistence(proof_of_existence::Call::RevokeClaim {
+                claim,
+            }),
+        ),
+        sign(
+            &bob,
+            bob_nonce + 1,
+            RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
+                claim,
+                description: None,
+                expires_at: None,
+            }),
+        ),
+        // Alice holds the sudo key of the dev chain, so she can dispatch root-only calls.
+        sign(
+            &alice,
+            alice_nonce + 4,
+            RuntimeCall::Sudo(sudo::Call::Sudo {
+                call: Box::new(RuntimeCall::Balances(balances::Call::ForceSetBalance {
+                    who: charlie,
+                    amount: 1_000_000,
+                })),
+            }),
+        ),
+    ];
+
+    // The author builds the blocks, filling in their headers.
+    // Our node then executes them, checking them against their headers, and stores them.
+    // Blocks reach the node encoded, as they would when received from another node.
+    // If there are any errors, our system panics, since we should not execute invalid blocks.
+    // After each block, we print the events it emitted.
+    for extrinsics in [extrinsics_1, extrinsics_2, extrinsics_3] {
+        let block = author.build_block(extrinsics).expect("invalid extrinsics");
+        let encoded = block.encode();
+        let block = Block::decode_all(&encoded).expect("invalid block encoding");
+        node.import_block(block).expect("invalid block");
+        for record in node.runtime.system.events() {
+            println!("{:?}: {:?}", record.phase, record.event);
+        }
+    }
+    let runtime = &node.runtime;
+
+    // Claims can be looked up by the content they claim, e.g. the bytes of a file.
+    let claim = runtime.proof_of_existence.claim_of_bytes(content);
+    println!("Claim of {:?}: {claim:?}", String::from_utf8_lossy(content));
+    // Claims can also be listed by their owner.
+    let claims = runtime.proof_of_existence.claims_of(&bob.account_id());
+    println!("Claims of bob: {claims:?}");
+
+    // A light client trusting only the state root of the latest header can check proofs of the
+    // state provided by our runtime, such as the balance of bob and the owner of the claim. The
+    // node looks the header up by its hash, as a light client would request it.
+    let best_hash = runtime.system.parent_hash();
+    let header = node.database.header_by_hash(&best_hash).expect("the latest block is stored");
+    let state_root = header.state_root;
+    let bob_key = runtime.balances.balance_key(&bob.account_id());
+    let bob_proof = runtime.balances.balance_proof(&bob.account_id());
+    let balance =
+        verify_proof::<Balance>(&state_root, &bob_key, &bob_proof).expect("invalid proof");
+    println!("Proven balance of bob: {balance:?}");
+    let claim = proof_of_existence::Pallet::<Runtime>::claim_for_bytes(content);
+    let claim_key = runtime.proof_of_existence.claim_key(&claim);
+    let claim_proof = runtime.proof_of_existence.claim_proof(&claim);
+    let claim = verify_proof::<proof_of_existence::Claim<AccountId, BlockNumber>>(
+        &state_root,
+        &claim_key,
+        &claim_proof,
+    )
+    .expect("invalid proof");
+    println!("Proven claim of {:?}: {claim:?}", String::from_utf8_lossy(content));
+
+    // Simply print the debug format of our runtime state.
+    println!("{runtime:#?}");
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		balances,
+		codec::{Decode, Encode, Error},
+		crypto::{Keyring, MultiSignature},
+		proof_of_existence,
+		storage::{self, ProofError, verify_proof},
+		support::{
+			BlockError, BlockResult, DispatchError, GetDispatchInfo, Header, InvalidTransaction, RawOrigin,
+			ReservableCurrency, Transactional, extrinsics_root,
+		},
+		system,
+		transaction_payment::{compute_fee, weight_fee},
+		types::{
+			EventRecord, Phase, Runtime, RuntimeCall, RuntimeEvent,
+			types::{AccountId, Balance, Block, BlockNumber, UncheckedExtrinsic},
+		},
+		utility,
+	};
+
+	type Poe = proof_of_existence::Pallet<Runtime>;
+
+	type Claim = proof_of_existence::Claim<AccountId, BlockNumber>;
+
+	/// The deposit reserved for every claim.
+	const CLAIM_DEPOSIT: Balance = <Runtime as proof_of_existence::Config>::CLAIM_DEPOSIT;
+
+	/// Sign `call` on behalf of `who`, at the latest block of `runtime`.
+	fn signed(runtime: &Runtime, who: Keyring, nonce: u32, call: RuntimeCall) -> UncheckedExtrinsic {
+		let (birth, birth_hash) = (runtime.system.block_number(), runtime.system.parent_hash());
+		UncheckedExtrinsic::new_signed(&who.pair(), nonce, call, birth, &birth_hash)
 	}
 
-	fn execute_block(&mut self, block: Block) -> DispatchResult {
-		self.system.inc_block_number();
-		if block.header.block_number != self.system.block_number() {
-			return Err("Block number mismatch");
-		}
+	/// A block of `extrinsics` on top of the latest block of `runtime`, with a placeholder state
+	/// root. Handy for blocks which are rejected before their state root is checked.
+	fn draft_block(runtime: &Runtime, extrinsics: Vec<UncheckedExtrinsic>) -> Block {
+		let header = Header {
+			parent_hash: runtime.system.parent_hash(),
+			block_number: runtime.system.next_block_number(),
+			state_root: Default::default(),
+			extrinsics_root: extrinsics_root(&extrinsics),
+			digest: Default::default(),
+		};
+		Block { header, extrinsics }
+	}
 
-		for (i, Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
-			self.system.inc_nonce(&caller);
-			let _result = self.dispatch(caller, call).map_err(|e| {
-				eprintln!(
-					"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
-					block.header.block_number, i, e
-				)
-			});
-		}
-		Ok(())
+	/// Build the next block of `runtime` out of `extrinsics`, checking the total issuance after.
+	fn build_block(runtime: &mut Runtime, extrinsics: Vec<UncheckedExtrinsic>) -> Block {
+		let block = runtime.build_block(extrinsics).expect("invalid block");
+		runtime.balances.assert_total_issuance();
+		block
 	}
-}
 
-impl crate::support::Dispatch for Runtime {
-	type Caller = <Runtime as SystemConfig>::AccountId;
-	type Call = RuntimeCall;
-	// Dispatch a call on behalf of a caller. Increments the caller's nonce.
-	//
-	// Dispatch allows us to identify which underlying module call we want to execute.
-	// Note that we extract the `caller` from the extrinsic, and use that information
-	// to determine who we are executing the call on behalf of.
-	fn dispatch(
-		&mut self,
-		caller: Self::Caller,
-		runtime_call: Self::Call,
-	) -> support::DispatchResult {
-		match runtime_call {
-			RuntimeCall::Balances(call) => {
-				self.balances.dispatch(caller, call)?;
-			},
-			RuntimeCall::ProofOfExistence(call) => {
-                self.proof_of_existence.dispatch(caller, call)?;
-            },
+	/// Execute `block` on `runtime`, checking the total issuance after, even if it is rejected.
+	fn execute_block(runtime: &mut Runtime, block: Block) -> BlockResult {
+		let result = runtime.execute_block(block);
+		runtime.balances.assert_total_issuance();
+		result
+	}
+
+	/// The fee paid for `extrinsic`, if its call uses all of its declared weight.
+	fn fee(extrinsic: &UncheckedExtrinsic) -> Balance {
+		let weight = extrinsic.call.get_dispatch_info().weight;
+		compute_fee::<Runtime>(extrinsic.encode().len() as u32, weight).unwrap()
+	}
+
+	fn transfer(to: Keyring, amount: u128) -> RuntimeCall {
+		RuntimeCall::Balances(balances::Call::Transfer { to: to.to_account_id(), amount })
+	}
+
+	fn create_claim(content: &str) -> RuntimeCall {
+		let claim = Poe::claim_for_bytes(content.as_bytes());
+		RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
+			claim,
+			description: None,
+			expires_at: None,
+		})
+	}
+
+	#[test]
+	fn failed_extrinsic_only_bumps_nonce_and_pays_the_fee() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let bob = Keyring::Bob.to_account_id();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+
+		// Once the fee is paid, alice can no longer afford the transfer.
+		let extrinsic = signed(&runtime, Keyring::Alice, 0, transfer(Keyring::Bob, 100_000));
+		let fee = fee(&extrinsic);
+		build_block(&mut runtime, vec![extrinsic]);
+
+		assert_eq!(runtime.balances.balance(&alice), 100_000 - fee);
+		assert_eq!(runtime.balances.balance(&bob), 0);
+		assert_eq!(runtime.system.nonce(&alice), 1);
+		assert_eq!(
+			runtime.system.extrinsic_events(0).last(),
+			Some(&RuntimeEvent::System(system::Event::ExtrinsicFailed {
+				error: balances::Error::<Runtime>::InsufficientBalance.into(),
+			}))
+		);
+	}
+
+	#[test]
+	fn pallet_errors_identify_pallet_and_variant() {
+		let mut runtime = Runtime::new();
+		let result = crate::support::Dispatch::dispatch(
+			&mut runtime,
+			RawOrigin::Signed(Keyring::Alice.to_account_id()),
+			transfer(Keyring::Bob, 1),
+		);
+
+		let result = result.map_err(|error| error.error);
+		assert_eq!(result, Err(DispatchError::Module { pallet_index: 1, error_index: 0 }));
+		assert_eq!(result, Err(balances::Error::<Runtime>::InsufficientBalance.into()));
+	}
+
+	#[test]
+	fn transaction_is_atomic_across_pallets() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let claim = Poe::claim_for_bytes(b"Hello, world!");
+		runtime.balances.set_balance(&alice, 20_000).unwrap();
+		Poe::create_claim(&mut runtime, RawOrigin::Signed(alice), claim, None, None).unwrap();
+
+		let result = runtime.with_transaction(|runtime| {
+			runtime.balances.set_balance(&alice, 50_000).unwrap();
+			runtime.system.inc_nonce(&alice);
+			Poe::revoke_claim(runtime, RawOrigin::Signed(alice), claim)?;
+			Poe::create_claim(runtime, RawOrigin::Signed(alice), claim, None, None)?;
+			Poe::create_claim(runtime, RawOrigin::Signed(alice), claim, None, None)
+		});
+
+		assert_eq!(
+			result.map_err(|error| error.error),
+			Err(proof_of_existence::Error::<Runtime>::ClaimAlreadyExists.into())
+		);
+		assert_eq!(runtime.balances.balance(&alice), 10_000);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10_000);
+		assert_eq!(runtime.system.nonce(&alice), 0);
+		let owner = runtime.proof_of_existence.get_claim(&claim).map(|claim| claim.owner);
+		assert_eq!(owner, Some(alice));
+	}
+
+	#[test]
+	fn nested_dispatch_keeps_outer_changes() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let claim = Poe::claim_for_bytes(b"Hello, world!");
+
+		let result = runtime.with_transaction(|runtime| {
+			runtime.balances.set_balance(&alice, 10_000).unwrap();
+			let call = proof_of_existence::Call::RevokeClaim { claim };
+			let inner = crate::support::Dispatch::dispatch(
+				runtime,
+				RawOrigin::Signed(alice),
+				RuntimeCall::ProofOfExistence(call),
+			);
+			let inner = inner.map_err(|error| error.error);
+			assert_eq!(inner, Err(DispatchError::Module { pallet_index: 2, error_index: 1 }));
+			Ok::<_, DispatchError>(())
+		});
+
+		assert_eq!(result, Ok(()));
+		assert_eq!(runtime.balances.balance(&alice), 10_000);
+		assert_eq!(runtime.proof_of_existence.get_claim(&claim), None);
+	}
+
+	#[test]
+	fn events_are_recorded_per_extrinsic_and_cleared_per_block() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let bob = Keyring::Bob.to_account_id();
+		runtime.balances.set_balance(&alice, 1_000_000).unwrap();
+
+		let extrinsics = vec![
+			signed(&runtime, Keyring::Alice, 0, transfer(Keyring::Bob, 100_000)),
+			signed(&runtime, Keyring::Bob, 0, create_claim("Hello, world!")),
+			signed(&runtime, Keyring::Alice, 1, create_claim("Hello, world!")),
+		];
+		build_block(&mut runtime, extrinsics);
+
+		assert_eq!(
+			runtime.system.events(),
+			&[
+				EventRecord {
+					phase: Phase::ApplyExtrinsic(0),
+					event: RuntimeEvent::Balances(balances::Event::Transfer {
+						from: alice,
+						to: bob,
+						amount: 100_000,
+					}),
+				},
+				EventRecord {
+					phase: Phase::ApplyExtrinsic(0),
+					event: RuntimeEvent::System(system::Event::ExtrinsicSuccess),
+				},
+				EventRecord {
+					phase: Phase::ApplyExtrinsic(1),
+					event: RuntimeEvent::Balances(balances::Event::Reserved {
+						who: bob,
+						amount: CLAIM_DEPOSIT,
+					}),
+				},
+				EventRecord {
+					phase: Phase::ApplyExtrinsic(1),
+					event: RuntimeEvent::ProofOfExistence(proof_of_existence::Event::ClaimCreated {
+						owner: bob,
+						claim: Poe::claim_for_bytes(b"Hello, world!"),
+					}),
+				},
+				EventRecord {
+					phase: Phase::ApplyExtrinsic(1),
+					event: RuntimeEvent::System(system::Event::ExtrinsicSuccess),
+				},
+				// The failed extrinsic only reports its error.
+				EventRecord {
+					phase: Phase::ApplyExtrinsic(2),
+					event: RuntimeEvent::System(system::Event::ExtrinsicFailed {
+						error: proof_of_existence::Error::<Runtime>::ClaimAlreadyExists.into(),
+					}),
+				},
+			]
+		);
+
+		build_block(&mut runtime, vec![]);
+		assert!(runtime.system.events().is_empty());
+	}
+
+	#[test]
+	fn claims_expire_when_their_block_is_finalized() {
+		let mut runtime = Runtime::new();
+		let mut author = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		runtime.balances.set_balance(&alice, 10_000_000).unwrap();
+		author.balances.set_balance(&alice, 10_000_000).unwrap();
+
+		let claim = Poe::claim_for_bytes(b"Hello, world!");
+		let call = RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
+			claim,
+			description: None,
+			expires_at: Some(2),
+		});
+		let extrinsic = signed(&author, Keyring::Alice, 0, call);
+		let block_1 = build_block(&mut author, vec![extrinsic]);
+		execute_block(&mut runtime, block_1).expect("invalid block");
+		assert!(runtime.proof_of_existence.get_claim(&claim).is_some());
+
+		// Removing the claim counts towards the weight of block 2, so it no longer holds a full
+		// block of transfers.
+		let extrinsics = (1..101)
+			.map(|nonce| signed(&runtime, Keyring::Alice, nonce, transfer(Keyring::Bob, 1_000)))
+			.collect::<Vec<_>>();
+		let block = draft_block(&runtime, extrinsics);
+		assert_eq!(
+			execute_block(&mut runtime, block),
+			Err(BlockError::InvalidTransaction {
+				extrinsic_index: 99,
+				error: InvalidTransaction::ExhaustsResources
+			})
+		);
+
+		// The claim is removed once the extrinsics of block 2 have been dispatched, and the state
+		// root of the block commits to its removal.
+		let block_2 = build_block(&mut author, vec![]);
+		execute_block(&mut runtime, block_2).expect("invalid block");
+		assert_eq!(runtime.proof_of_existence.get_claim(&claim), None);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		assert_eq!(
+			runtime.system.events(),
+			&[
+				EventRecord {
+					phase: Phase::Finalization,
+					event: RuntimeEvent::Balances(balances::Event::Unreserved {
+						who: alice,
+						amount: CLAIM_DEPOSIT,
+					}),
+				},
+				EventRecord {
+					phase: Phase::Finalization,
+					event: RuntimeEvent::ProofOfExistence(proof_of_existence::Event::ClaimExpired {
+						owner: alice,
+						claim,
+					}),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn replayed_and_future_extrinsics_reject_the_block() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let bob = Keyring::Bob.to_account_id();
+		runtime.balances.set_balance(&alice, 1_000_000).unwrap();
+
+		let extrinsic = signed(&runtime, Keyring::Alice, 0, transfer(Keyring::Bob, 10_000));
+		let fee = fee(&extrinsic);
+		build_block(&mut runtime, vec![extrinsic]);
+
+		// Replaying the same extrinsic in the next block is rejected.
+		let extrinsics = vec![signed(&runtime, Keyring::Alice, 0, transfer(Keyring::Bob, 10_000))];
+		let block_2 = draft_block(&runtime, extrinsics);
+		assert_eq!(
+			execute_block(&mut runtime, block_2),
+			Err(BlockError::InvalidTransaction {
+				extrinsic_index: 0,
+				error: InvalidTransaction::Stale
+			})
+		);
+
+		// Skipping a nonce is rejected too, even after valid extrinsics.
+		let extrinsics = vec![
+			signed(&runtime, Keyring::Alice, 1, transfer(Keyring::Bob, 10_000)),
+			signed(&runtime, Keyring::Alice, 3, transfer(Keyring::Bob, 10_000)),
+		];
+		let block_2 = draft_block(&runtime, extrinsics);
+		assert_eq!(
+			execute_block(&mut runtime, block_2),
+			Err(BlockError::InvalidTransaction {
+				extrinsic_index: 1,
+				error: InvalidTransaction::Future
+			})
+		);
+
+		// Rejected blocks leave no changes behind.
+		assert_eq!(runtime.system.block_number(), 1);
+		assert_eq!(runtime.system.nonce(&alice), 1);
+		assert_eq!(runtime.balances.balance(&alice), 1_000_000 - 10_000 - fee);
+
+		let extrinsics = vec![
+			signed(&runtime, Keyring::Alice, 1, transfer(Keyring::Bob, 10_000)),
+			signed(&runtime, Keyring::Alice, 2, transfer(Keyring::Bob, 10_000)),
+		];
+		build_block(&mut runtime, extrinsics);
+		assert_eq!(runtime.system.nonce(&alice), 3);
+		assert_eq!(runtime.balances.balance(&bob), 30_000);
+	}
+
+	#[test]
+	fn reaped_accounts_can_not_replay_expired_extrinsics() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let bob = Keyring::Bob.to_account_id();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+
+		// Alice sends everything she has left once the fee is paid, and is reaped with her nonce.
+		let call = balances::Call::TransferAll { to: bob, keep_alive: false };
+		let extrinsic = signed(&runtime, Keyring::Alice, 0, RuntimeCall::Balances(call));
+		let fee = fee(&extrinsic);
+		build_block(&mut runtime, vec![extrinsic.clone()]);
+		assert_eq!(runtime.balances.balance(&alice), 0);
+		assert_eq!(runtime.balances.balance(&bob), 100_000 - fee);
+		assert_eq!(runtime.system.nonce(&alice), 0);
+
+		// Once the extrinsic has expired, funding her again does not let anyone replay it.
+		let period = <Runtime as crate::types::SystemConfig>::MORTALITY_PERIOD;
+		for _ in 1..period {
+			build_block(&mut runtime, vec![]);
 		}
-		Ok(())
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+		let block = draft_block(&runtime, vec![extrinsic]);
+		assert_eq!(
+			execute_block(&mut runtime, block),
+			Err(BlockError::InvalidTransaction {
+				extrinsic_index: 0,
+				error: InvalidTransaction::AncientBirthBlock
+			})
+		);
+		assert_eq!(runtime.balances.balance(&alice), 100_000);
+
+		// Her new extrinsics start over from the first nonce.
+		let extrinsic = signed(&runtime, Keyring::Alice, 0, transfer(Keyring::Bob, 10_000));
+		build_block(&mut runtime, vec![extrinsic]);
+		assert_eq!(runtime.system.nonce(&alice), 1);
 	}
-}
 
-fn main() {
-    // Create a new instance of the Runtime.
-    // It will instantiate with it all the modules it uses.
-    let mut runtime = Runtime::new();
-    let alice = "alice".to_string();
-    let bob = "bob".to_string();
-    let charlie = "charlie".to_string();
+	#[test]
+	fn extrinsics_with_invalid_signatures_reject_the_block() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+		let execute = |runtime: &mut Runtime, extrinsic| {
+			let block = draft_block(runtime, vec![extrinsic]);
+			execute_block(runtime, block)
+		};
+		let bad_proof = Err(BlockError::InvalidTransaction {
+			extrinsic_index: 0,
+			error: InvalidTransaction::BadProof,
+		});
 
-    // Initialize the system with some initial balance.
-    runtime.balances.set_balance(&alice, 100);
+		// Bob can not pretend to be Alice.
+		let mut extrinsic = signed(&runtime, Keyring::Bob, 0, transfer(Keyring::Bob, 10_000));
+		extrinsic.signer = alice;
+		assert_eq!(execute(&mut runtime, extrinsic), bad_proof);
 
-    // Here are the extrinsics in our block.
-    // You can add or remove these based on the modules and calls you have set up.
-    let block_1 =  crate::support::Block {
-        header: support::Header { block_number: 1 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::Balances(balances::Call::Transfer {
-                    to: bob.clone(),
-                    amount: 30,
-                }),
-            },
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::Balances(balances::Call::Transfer { to: charlie, amount: 20 }),
-            },
-        ],
-    };
-
-    let block_2 = crate::support::Block {
-        header: support::Header { block_number: 2 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
-                    claim: "Hello, world!".to_string(),
-                }),
-            },
-            support::Extrinsic {
-                caller: bob.clone(),
-                call: RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
-                    claim: "Hello, world!".to_string(),
-                }),
-            },
-        ],
-    };
-
-    let block_3 = crate::support::Block {
-        header: support::Header { block_number: 3 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice,
-                call: RuntimeCall::ProofOfExistence(proof_of_existence::Call::RevokeClaim {
-                    claim: "Hello, world!".to_string(),
-                }),
-            },
-            support::Extrinsic {
-                caller: bob,
-                call: RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
-                    claim: "Hello, world!".to_string(),
-                }),
-            },
-        ],
-    };
-
-    // Execute the extrinsics which make up our blocks.
-    // If there are any errors, our system panics, since we should not execute invalid blocks.
-    runtime.execute_block(block_1).expect("invalid block");
-    runtime.execute_block(block_2).expect("invalid block");
-    runtime.execute_block(block_3).expect("invalid block");
+		// The call can not be changed after signing.
+		let mut extrinsic = signed(&runtime, Keyring::Alice, 0, transfer(Keyring::Bob, 10_000));
+		extrinsic.call = transfer(Keyring::Bob, 100_000);
+		assert_eq!(execute(&mut runtime, extrinsic), bad_proof);
 
-    // Simply print the debug format of our runtime state.
-    println!("{runtime:#?}");
-}
\ No newline at end of file
+		// Extrinsics signed for another chain are rejected.
+		let extrinsic = UncheckedExtrinsic::new_signed(
+			&Keyring::Alice.pair(),
+			0,
+			transfer(Keyring::Bob, 10_000),
+			0,
+			&[0; 32],
+		);
+		assert_eq!(execute(&mut runtime, extrinsic), bad_proof);
+
+		// Ed25519 signatures are supported too.
+		let charlie = Keyring::Charlie.ed25519_pair();
+		runtime.balances.set_balance(&charlie.account_id(), 100_000).unwrap();
+		let extrinsic = UncheckedExtrinsic::new_signed(
+			&charlie,
+			0,
+			transfer(Keyring::Bob, 10_000),
+			0,
+			&runtime.system.genesis_hash(),
+		);
+		assert!(matches!(extrinsic.signature, MultiSignature::Ed25519(_)));
+		build_block(&mut runtime, vec![extrinsic]);
+		assert_eq!(runtime.balances.balance(&Keyring::Bob.to_account_id()), 10_000);
+	}
+
+	#[test]
+	fn blocks_round_trip_through_the_codec() {
+		let mut runtime = Runtime::new();
+		let mut author = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+		author.balances.set_balance(&alice, 100_000).unwrap();
+		let extrinsics = vec![
+			signed(&runtime, Keyring::Alice, 0, transfer(Keyring::Bob, 30_000)),
+			signed(&runtime, Keyring::Alice, 1, create_claim("Hello, world!")),
+		];
+		let fees = extrinsics.iter().map(fee).sum::<Balance>();
+		let block = build_block(&mut author, extrinsics);
+
+		// Calls are encoded as the pallet index, the call index and the arguments.
+		let call = transfer(Keyring::Bob, 30_000).encode();
+		assert_eq!(call[..2], [1, 0]);
+		assert_eq!(call[2..34], Keyring::Bob.to_account_id().0);
+		assert_eq!(call[34..], 30_000u128.to_le_bytes());
+
+		let encoded = block.encode();
+		let decoded = Block::decode_all(&encoded).expect("invalid block encoding");
+		assert_eq!(decoded.encode(), encoded);
+
+		// The decoded block still carries valid signatures, and matches its header.
+		execute_block(&mut runtime, decoded).expect("invalid block");
+		assert_eq!(runtime.balances.balance(&alice), 100_000 - 30_000 - fees - CLAIM_DEPOSIT);
+		assert_eq!(runtime.system.nonce(&alice), 2);
+
+		// Unknown pallets or calls, and truncated blocks are rejected.
+		assert!(matches!(RuntimeCall::decode_all(&[0]), Err(Error::Invalid(_))));
+		assert!(matches!(RuntimeCall::decode_all(&[1, 9]), Err(Error::Invalid(_))));
+		let truncated = Block::decode_all(&encoded[..100]);
+		assert_eq!(truncated.err(), Some(Error::UnexpectedEnd));
+	}
+
+	#[test]
+	fn blocks_are_checked_against_their_header() {
+		let mut runtime = Runtime::new();
+		let mut author = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+		author.balances.set_balance(&alice, 100_000).unwrap();
+
+		let block_1 = build_block(&mut author, vec![]);
+		execute_block(&mut runtime, block_1.clone()).expect("invalid block");
+		let extrinsics = vec![
+			signed(&runtime, Keyring::Alice, 0, transfer(Keyring::Bob, 30_000)),
+			signed(&runtime, Keyring::Alice, 1, transfer(Keyring::Charlie, 20_000)),
+		];
+		let fees = extrinsics.iter().map(fee).sum::<Balance>();
+		let block_2 = build_block(&mut author, extrinsics);
+		assert_eq!(block_2.header.parent_hash, block_1.header.hash());
+		assert_eq!(author.system.parent_hash(), block_2.header.hash());
+
+		// The block must build on the latest block.
+		let mut block = block_2.clone();
+		block.header.parent_hash = block_2.header.hash();
+		assert_eq!(execute_block(&mut runtime, block), Err(BlockError::InvalidParentHash));
+
+		// The extrinsics can not be changed or left out.
+		let mut block = block_2.clone();
+		block.extrinsics.pop();
+		assert_eq!(execute_block(&mut runtime, block), Err(BlockError::InvalidExtrinsicsRoot));
+
+		// The resulting state must match, and a rejected block leaves no changes behind.
+		let mut block = block_2.clone();
+		block.header.state_root = block_1.header.state_root;
+		assert_eq!(execute_block(&mut runtime, block), Err(BlockError::InvalidStateRoot));
+		assert_eq!(runtime.system.block_number(), 1);
+		assert_eq!(runtime.system.nonce(&alice), 0);
+		assert_eq!(runtime.balances.balance(&alice), 100_000);
+		assert_eq!(runtime.system.parent_hash(), block_1.header.hash());
+
+		execute_block(&mut runtime, block_2.clone()).expect("invalid block");
+		assert_eq!(runtime.state_root(), block_2.header.state_root);
+		assert_eq!(runtime.system.block_hash(2), Some(block_2.header.hash()));
+		assert_eq!(runtime.balances.balance(&alice), 100_000 - 50_000 - fees);
+		assert_eq!(runtime.system.events().len(), 4);
+	}
+
+	#[test]
+	fn storage_can_be_proven_against_a_header() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let bob = Keyring::Bob.to_account_id();
+		let charlie = Keyring::Charlie.to_account_id();
+		runtime.balances.set_balance(&alice, 1_000_000).unwrap();
+		let extrinsics = vec![
+			signed(&runtime, Keyring::Alice, 0, transfer(Keyring::Bob, 100_000)),
+			signed(&runtime, Keyring::Alice, 1, create_claim("Hello, world!")),
+		];
+		let header = build_block(&mut runtime, extrinsics).header;
+
+		// A light client only trusting the header can check the balance of bob, knowing the names of
+		// the pallet and of the storage item...
+		let bob_key = runtime.balances.balance_key(&bob);
+		assert_eq!(bob_key, [storage::prefix("Balances", "Balances"), bob.encode()].concat());
+		let bob_proof = runtime.balances.balance_proof(&bob);
+		assert_eq!(
+			verify_proof::<Balance>(&header.state_root, &bob_key, &bob_proof),
+			Ok(Some(100_000))
+		);
+		// ...that alice claimed some content in this block...
+		let claim = Poe::claim_for_bytes(b"Hello, world!");
+		let claim_key = runtime.proof_of_existence.claim_key(&claim);
+		let claim_proof = runtime.proof_of_existence.claim_proof(&claim);
+		assert_eq!(
+			verify_proof::<Claim>(&header.state_root, &claim_key, &claim_proof),
+			Ok(Some(Claim { owner: alice, block_number: 1, description: None, expires_at: None }))
+		);
+		// ...and that charlie has no balance, and nobody owns another claim.
+		let charlie_key = runtime.balances.balance_key(&charlie);
+		let charlie_proof = runtime.balances.balance_proof(&charlie);
+		assert_eq!(
+			verify_proof::<Balance>(&header.state_root, &charlie_key, &charlie_proof),
+			Ok(None)
+		);
+		let other_claim = Poe::claim_for_bytes(b"Goodbye, world!");
+		let other_key = runtime.proof_of_existence.claim_key(&other_claim);
+		let other_proof = runtime.proof_of_existence.claim_proof(&other_claim);
+		assert_eq!(
+			verify_proof::<Claim>(&header.state_root, &other_key, &other_proof),
+			Ok(None)
+		);
+
+		// Tampered proofs are rejected.
+		let mut tampered = bob_proof.clone();
+		tampered.value = Some(1000u128.encode());
+		assert_eq!(
+			verify_proof::<Balance>(&header.state_root, &bob_key, &tampered),
+			Err(ProofError::InvalidProof)
+		);
+		let mut tampered = claim_proof.clone();
+		let forged = Claim { owner: charlie, block_number: 1, description: None, expires_at: None };
+		tampered.value = Some(forged.encode());
+		assert_eq!(
+			verify_proof::<Claim>(&header.state_root, &claim_key, &tampered),
+			Err(ProofError::InvalidProof)
+		);
+		// Neither can an existing entry be proven absent, nor a proof be used for another key.
+		let mut tampered = bob_proof.clone();
+		tampered.value = None;
+		assert_eq!(
+			verify_proof::<Balance>(&header.state_root, &bob_key, &tampered),
+			Err(ProofError::InvalidProof)
+		);
+		assert_eq!(
+			verify_proof::<Balance>(&header.state_root, &charlie_key, &bob_proof),
+			Err(ProofError::InvalidProof)
+		);
+
+		// Proofs only hold against the state they were made for.
+		let extrinsic = signed(&runtime, Keyring::Bob, 0, transfer(Keyring::Charlie, 10_000));
+		let fee = fee(&extrinsic);
+		let header = build_block(&mut runtime, vec![extrinsic]).header;
+		assert_eq!(
+			verify_proof::<Balance>(&header.state_root, &bob_key, &bob_proof),
+			Err(ProofError::InvalidProof)
+		);
+		let bob_proof = runtime.balances.balance_proof(&bob);
+		assert_eq!(
+			verify_proof::<Balance>(&header.state_root, &bob_key, &bob_proof),
+			Ok(Some(100_000 - 10_000 - fee))
+		);
+	}
+
+	#[test]
+	fn extrinsics_pay_for_their_weight_and_length() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let bob = Keyring::Bob.to_account_id();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+		runtime.balances.set_balance(&bob, 100_000).unwrap();
+
+		let alice_transfer = signed(&runtime, Keyring::Alice, 0, transfer(Keyring::Bob, 10_000));
+		let len = alice_transfer.encode().len() as u32;
+		assert_eq!(alice_transfer.call.get_dispatch_info().weight, 10_000);
+		assert_eq!(fee(&alice_transfer), compute_fee::<Runtime>(len, 10_000).unwrap());
+		let alice_claim = signed(&runtime, Keyring::Alice, 1, create_claim("Hello, world!"));
+		let bob_claim = signed(&runtime, Keyring::Bob, 0, create_claim("Hello, world!"));
+		let fees = [fee(&alice_transfer), fee(&alice_claim), fee(&bob_claim)];
+		build_block(&mut runtime, vec![alice_transfer, alice_claim, bob_claim]);
+
+		// Bob's claim fails early, so bob is refunded for the weight it did not use.
+		assert_eq!(
+			runtime.balances.balance(&alice),
+			100_000 - 10_000 - fees[0] - fees[1] - CLAIM_DEPOSIT
+		);
+		let refund = weight_fee::<Runtime>(20_000 - 5_000).unwrap();
+		assert_eq!(runtime.balances.balance(&bob), 100_000 + 10_000 - fees[2] + refund);
+
+		// Extrinsics whose caller can not pay the fee reject the block, leaving no changes behind.
+		let charlie = signed(&runtime, Keyring::Charlie, 0, transfer(Keyring::Bob, 0));
+		let block = draft_block(&runtime, vec![charlie]);
+		assert_eq!(
+			execute_block(&mut runtime, block),
+			Err(BlockError::InvalidTransaction {
+				extrinsic_index: 0,
+				error: InvalidTransaction::Payment
+			})
+		);
+		assert_eq!(runtime.system.nonce(&Keyring::Charlie.to_account_id()), 0);
+	}
+
+	#[test]
+	fn batches_are_dispatched_in_a_single_extrinsic() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		runtime.balances.set_balance(&alice, 1_000_000).unwrap();
+
+		// Many transfers from one account fit in one extrinsic, which pays for all of them.
+		let calls = (0..20).map(|_| transfer(Keyring::Bob, 1_000)).collect();
+		let call = RuntimeCall::Utility(utility::Call::Batch { calls });
+		let extrinsic = signed(&runtime, Keyring::Alice, 0, call);
+		assert_eq!(extrinsic.call.get_dispatch_info().weight, 210_000);
+		let encoded = extrinsic.encode();
+		assert_eq!(UncheckedExtrinsic::decode_all(&encoded).unwrap().encode(), encoded);
+		let fee = fee(&extrinsic);
+		build_block(&mut runtime, vec![extrinsic]);
+
+		assert_eq!(runtime.balances.balance(&Keyring::Bob.to_account_id()), 20_000);
+		assert_eq!(runtime.balances.balance(&alice), 1_000_000 - 20_000 - fee);
+		assert_eq!(runtime.system.nonce(&alice), 1);
+		// Every call of the batch reports its result, as part of the same extrinsic.
+		let events = runtime.system.extrinsic_events(0).collect::<Vec<_>>();
+		assert_eq!(events.len(), 20 + 20 + 2);
+		assert_eq!(
+			events[events.len() - 2..],
+			[
+				&RuntimeEvent::Utility(utility::Event::BatchCompleted),
+				&RuntimeEvent::System(system::Event::ExtrinsicSuccess),
+			]
+		);
+	}
+
+	#[test]
+	fn blocks_are_limited_in_weight() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		runtime.balances.set_balance(&alice, 10_000_000).unwrap();
+		runtime.balances.set_balance(&Keyring::Charlie.to_account_id(), 100_000).unwrap();
+
+		// Transfers weigh 10_000, so a full block holds 100 of them.
+		let extrinsics = (0..101)
+			.map(|nonce| signed(&runtime, Keyring::Alice, nonce, transfer(Keyring::Bob, 1_000)))
+			.collect::<Vec<_>>();
+		let block = draft_block(&runtime, extrinsics.clone());
+		assert_eq!(
+			execute_block(&mut runtime, block),
+			Err(BlockError::InvalidTransaction {
+				extrinsic_index: 100,
+				error: InvalidTransaction::ExhaustsResources
+			})
+		);
+		build_block(&mut runtime, extrinsics[..100].to_vec());
+		assert_eq!(runtime.balances.balance(&Keyring::Bob.to_account_id()), 100_000);
+
+		// Weight which was declared but not used can be used by later extrinsics. Charlie's claim
+		// declares 20_000 but only uses 5_000, leaving room for 99 transfers.
+		let claim = signed(&runtime, Keyring::Alice, 100, create_claim("Hello, world!"));
+		build_block(&mut runtime, vec![claim]);
+		let claim = signed(&runtime, Keyring::Charlie, 0, create_claim("Hello, world!"));
+		let transfers = (101..200)
+			.map(|nonce| signed(&runtime, Keyring::Alice, nonce, transfer(Keyring::Bob, 1_000)));
+		let extrinsics = [claim].into_iter().chain(transfers).collect::<Vec<_>>();
+		build_block(&mut runtime, extrinsics);
+		assert_eq!(runtime.balances.balance(&Keyring::Bob.to_account_id()), 199_000);
+	}
+}
@@ -0,0 +1,296 @@
+//! A node, executing blocks with a runtime and persisting them in a database.
+//!
+//! When a node is opened again, it resumes from the state stored in its database. That state is
+//! checked against the header of the block it was stored for, and any block stored after it is
+//! executed again, so the node never resumes from a state it has not verified.
+//!
+//! The genesis state is always built again from the genesis configuration, since the stored blocks
+//! are checked to build on the genesis block committing to it. A database created with another
+//! genesis configuration is rejected.
+
+use crate::{
+	database::{self, Database, State},
+	support::{BlockError, DispatchError, extrinsics_root},
+	types::{
+		GenesisConfig, Runtime,
+		types::{Block, BlockNumber, UncheckedExtrinsic},
+	},
+};
+use core::fmt::Display;
+use std::path::Path;
+
+/// Why a node could not open, or import a block.
+#[derive(Debug)]
+pub enum Error {
+	Database(database::Error),
+	/// A block was rejected by the runtime.
+	InvalidBlock {
+		block_number: BlockNumber,
+		error: BlockError,
+	},
+	/// The stored state does not match the stored blocks.
+	InvalidState,
+	/// The genesis configuration was rejected by the runtime.
+	InvalidGenesis(DispatchError),
+}
+
+impl From<database::Error> for Error {
+	fn from(error: database::Error) -> Self {
+		Self::Database(error)
+	}
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::Database(error) => write!(f, "database error: {error}"),
+			Self::InvalidBlock { block_number, error } => {
+				write!(f, "invalid block {block_number}: {error:?}")
+			},
+			Self::InvalidState => write!(f, "the stored state does not match the stored blocks"),
+			Self::InvalidGenesis(error) => write!(f, "invalid genesis configuration: {error:?}"),
+		}
+	}
+}
+
+/// A runtime along with the database persisting its chain.
+pub struct Node {
+	pub runtime: Runtime,
+	pub database: Database,
+}
+
+impl Node {
+	/// Open the node with its database in `dir`, resuming from the latest block stored in it.
+	///
+	/// The chain starts from the genesis block built from `genesis`, which must be the same every
+	/// time the node is opened.
+	pub fn open(dir: impl AsRef<Path>, genesis: &GenesisConfig) -> Result<Self, Error> {
+		let database = Database::open(dir)?;
+		let mut node = Self {
+			runtime: Runtime::from_genesis(genesis).map_err(Error::InvalidGenesis)?,
+			database,
+		};
+		let genesis_number = node.runtime.system.block_number();
+		let genesis_root = node.runtime.state_root();
+		let Some(state) = node.database.state()? else {
+			// The genesis state is stored before any block, so there can not be blocks yet.
+			if node.database.best_number().is_some() {
+				return Err(Error::InvalidState);
+			}
+			node.store_state()?;
+			return Ok(node);
+		};
+
+		// The state is never stored ahead of the blocks, nor before the genesis block.
+		let best_number = node.database.best_number().unwrap_or(genesis_number);
+		if state.block_number < genesis_number || state.block_number > best_number {
+			return Err(Error::InvalidState);
+		}
+
+		// The stored blocks must form a chain starting at the genesis block. Only the blocks up to
+		// the stored state are recorded here, the others are recorded as they are executed.
+		let mut parent_hash = node.runtime.system.genesis_hash();
+		let mut parent_number = genesis_number;
+		for block in node.database.blocks() {
+			let header = &block.header;
+			if header.parent_hash != parent_hash
+				|| header.block_number != parent_number + 1
+				|| header.extrinsics_root != extrinsics_root(&block.extrinsics)
+			{
+				return Err(Error::InvalidState);
+			}
+			parent_hash = header.hash();
+			parent_number = header.block_number;
+			if header.block_number <= state.block_number {
+				node.runtime.system.insert_block_hash(header.block_number, parent_hash);
+			}
+		}
+
+		// The stored state must be the one committed to by the header of its block, or by the
+		// genesis block.
+		let state_root = match node.database.header(state.block_number) {
+			Some(header) => header.state_root,
+			None => genesis_root,
+		};
+		node.runtime.system.storage.restore(state.entries);
+		if node.runtime.system.block_number() != state.block_number
+			|| node.runtime.state_root() != state_root
+		{
+			return Err(Error::InvalidState);
+		}
+
+		// Catch up with the blocks stored after the state.
+		let missing = node
+			.database
+			.blocks()
+			.iter()
+			.filter(|block| block.header.block_number > state.block_number)
+			.cloned()
+			.collect::<Vec<_>>();
+		for block in missing {
+			let block_number = block.header.block_number;
+			node.runtime
+				.execute_block(block)
+				.map_err(|error| Error::InvalidBlock { block_number, error })?;
+		}
+		node.store_state()?;
+		Ok(node)
+	}
+
+	/// Execute `block` and store it.
+	///
+	/// If storing the block fails, the runtime is ahead of the database until the node is opened
+	/// again.
+	pub fn import_block(&mut self, block: Block) -> Result<(), Error> {
+		let block_number = block.header.block_number;
+		self.runtime
+			.execute_block(block.clone())
+			.map_err(|error| Error::InvalidBlock { block_number, error })?;
+		self.database.append_block(block)?;
+		self.store_state()
+	}
+
+	/// Build the next block out of `extrinsics` and store it, as a block author would.
+	pub fn build_block(&mut self, extrinsics: Vec<UncheckedExtrinsic>) -> Result<Block, Error> {
+		let block = self.runtime.build_block(extrinsics).map_err(|error| Error::InvalidBlock {
+			block_number: self.runtime.system.next_block_number(),
+			error,
+		})?;
+		self.database.append_block(block.clone())?;
+		self.store_state()?;
+		Ok(block)
+	}
+
+	/// Store the current state of the runtime.
+	fn store_state(&self) -> Result<(), Error> {
+		let block_number = self.runtime.system.block_number();
+		let entries = self.runtime.system.storage.entries();
+		self.database.store_state(&State { block_number, entries })?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Error, Node};
+	use crate::{
+		balances,
+		crypto::Keyring,
+		database::{Database, State},
+		system,
+		types::{GenesisConfig, RuntimeCall, types::UncheckedExtrinsic},
+	};
+	use std::{fs, path::PathBuf};
+
+	/// A fresh directory for the test `name`.
+	fn test_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("node-{}-{name}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		dir
+	}
+
+	/// A chain starting at `block_number`, with alice endowed.
+	fn genesis(block_number: u32) -> GenesisConfig {
+		GenesisConfig {
+			system: system::GenesisConfig { block_number },
+			balances: balances::GenesisConfig {
+				balances: vec![(Keyring::Alice.to_account_id(), 1_000_000)],
+			},
+			..Default::default()
+		}
+	}
+
+	/// A transfer of `amount` from alice to bob, as the next extrinsic of alice on `node`.
+	fn transfer(node: &Node, amount: u128) -> UncheckedExtrinsic {
+		let alice = Keyring::Alice.to_account_id();
+		let call = RuntimeCall::Balances(balances::Call::Transfer {
+			to: Keyring::Bob.to_account_id(),
+			amount,
+		});
+		let (birth, birth_hash) =
+			(node.runtime.system.block_number(), node.runtime.system.parent_hash());
+		let nonce = node.runtime.system.nonce(&alice);
+		UncheckedExtrinsic::new_signed(&Keyring::Alice.pair(), nonce, call, birth, &birth_hash)
+	}
+
+	#[test]
+	fn nodes_resume_from_the_latest_block() {
+		let dir = test_dir("resume");
+		let mut author = Node::open(dir.join("author"), &genesis(0)).unwrap();
+		let mut node = Node::open(dir.join("node"), &genesis(0)).unwrap();
+		for amount in [10_000, 20_000] {
+			let block = author.build_block(vec![transfer(&author, amount)]).unwrap();
+			node.import_block(block).unwrap();
+		}
+		let state_root = node.runtime.state_root();
+		let parent_hash = node.runtime.system.parent_hash();
+		drop(node);
+
+		// A node can not be opened on a chain with another genesis block.
+		assert!(matches!(Node::open(dir.join("node"), &genesis(1)), Err(Error::InvalidState)));
+
+		// Blocks keep building on the latest one.
+		let mut node = Node::open(dir.join("node"), &genesis(0)).unwrap();
+		assert_eq!(node.runtime.system.block_number(), 2);
+		assert_eq!(node.runtime.state_root(), state_root);
+		assert_eq!(node.runtime.system.parent_hash(), parent_hash);
+		assert_eq!(node.runtime.balances.balance(&Keyring::Bob.to_account_id()), 30_000);
+		let block = author.build_block(vec![transfer(&author, 30_000)]).unwrap();
+		node.import_block(block).unwrap();
+		assert_eq!(node.runtime.state_root(), author.runtime.state_root());
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn nodes_catch_up_with_blocks_stored_after_the_state() {
+		let dir = test_dir("catch-up");
+		let mut node = Node::open(&dir, &genesis(0)).unwrap();
+		let state = node.database.state().unwrap().unwrap();
+		node.build_block(vec![transfer(&node, 10_000)]).unwrap();
+		let state_root = node.runtime.state_root();
+
+		// Simulate the node stopping after storing the block, but before storing its state.
+		node.database.store_state(&state).unwrap();
+		drop(node);
+		let node = Node::open(&dir, &genesis(0)).unwrap();
+		assert_eq!(node.runtime.state_root(), state_root);
+		assert_eq!(node.database.state().unwrap().unwrap().block_number, 1);
+
+		// A state which does not match its block is rejected.
+		let mut state = node.database.state().unwrap().unwrap();
+		state.entries.pop();
+		node.database.store_state(&state).unwrap();
+		drop(node);
+		assert!(matches!(Node::open(&dir, &genesis(0)), Err(Error::InvalidState)));
+		let state = State { block_number: 2, entries: vec![] };
+		Database::open(&dir).unwrap().store_state(&state).unwrap();
+		assert!(matches!(Node::open(&dir, &genesis(0)), Err(Error::InvalidState)));
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn chains_start_from_the_genesis_block_number() {
+		let dir = test_dir("genesis-number");
+		let mut author = Node::open(dir.join("author"), &genesis(10)).unwrap();
+		let mut node = Node::open(dir.join("node"), &genesis(10)).unwrap();
+		assert_eq!(node.runtime.system.block_number(), 10);
+		let state = node.database.state().unwrap().unwrap();
+		let block = author.build_block(vec![transfer(&author, 10_000)]).unwrap();
+		assert_eq!(block.header.block_number, 11);
+		node.import_block(block).unwrap();
+
+		// Catch up from the genesis state.
+		node.database.store_state(&state).unwrap();
+		drop(node);
+		let node = Node::open(dir.join("node"), &genesis(10)).unwrap();
+		assert_eq!(node.runtime.system.block_number(), 11);
+		assert_eq!(node.runtime.state_root(), author.runtime.state_root());
+
+		// The genesis state is checked against the genesis block.
+		drop(node);
+		let state = State { entries: vec![], ..state };
+		Database::open(dir.join("node")).unwrap().store_state(&state).unwrap();
+		assert!(matches!(Node::open(dir.join("node"), &genesis(10)), Err(Error::InvalidState)));
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
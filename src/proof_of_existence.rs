@@ -1,94 +1,765 @@
-use crate::support::DispatchResult;
+use crate::{
+	codec::{Decode, Encode},
+	crypto::Hasher,
+	storage::{Storage, StorageDoubleMap, StorageMap},
+	support::{
+		Currency, DispatchResult, DispatchResultWithPostInfo, GetPallet, Hooks, ReservableCurrency,
+		TransactionalVec, Weight, WithPostDispatchInfo, ensure_signed,
+	},
+	trie,
+	types::{SystemConfig, SystemPallet},
+};
 use core::fmt::Debug;
+use num::traits::{CheckedAdd, Saturating, Zero};
 use std::collections::BTreeMap;
 
-pub trait Config: crate::SystemConfig {
-	/// The type which represents the content that can be claimed using this pallet.
-	/// Could be the content directly as bytes, or better yet the hash of that content.
-	/// We leave that decision to the runtime developer.
-	type Content: Debug + Ord;
+pub trait Config: SystemConfig + Sized + GetPallet<Pallet<Self>>
+where
+	Self: GetPallet<Self::Currency>,
+{
+	/// The hash function identifying content by its hash. Only the hash of claimed content is
+	/// kept on chain, and is used as the key of its claim.
+	type Hashing: Hasher<Output: Debug + Ord + Clone + Encode + Decode>;
+	/// The currency held by accounts, e.g. the balances pallet, from which claim deposits are
+	/// reserved.
+	type Currency: ReservableCurrency<
+			Self::AccountId,
+			Balance: Copy + Ord + Zero + Saturating + Encode + Decode,
+		>;
+	/// The deposit reserved from the owner of a claim for as long as the claim exists.
+	const CLAIM_DEPOSIT: BalanceOf<Self>;
+	/// The longest description a claim can have, in bytes.
+	const MAX_DESCRIPTION_LEN: u32;
+	/// The most claims which can expire in the same block, bounding the weight of removing them.
+	const MAX_EXPIRIES_PER_BLOCK: u32;
+	/// The most blocks after the current block a claim can be set to expire at.
+	const MAX_EXPIRY: Self::BlockNumber;
 }
 
+/// The hash of some content, identifying its claim.
+pub type ContentHash<T> = <<T as Config>::Hashing as Hasher>::Output;
+
+/// The claim record of the pallet.
+pub type ClaimOf<T> = Claim<<T as SystemConfig>::AccountId, <T as SystemConfig>::BlockNumber>;
+
+/// The balance type of the currency of the pallet.
+pub type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as SystemConfig>::AccountId>>::Balance;
+
+/// The weight of removing an expired claim, which is the same as revoking it.
+const EXPIRE_CLAIM_WEIGHT: Weight = 10_000;
+
 /// This is the Proof of Existence Module.
 /// It is a simple module that allows accounts to claim existence of some data.
-#[derive(Debug)]
 pub struct Pallet<T: Config> {
-	/// A simple storage map from content to the owner of that content.
-	/// Accounts can make multiple different claims, but each claim can only have one owner.
-	pub claims: BTreeMap<T::Content, T::AccountId>,
+	/// The storage of the runtime, holding the storage items of this pallet.
+	pub storage: Storage,
+	/// The events deposited during the current extrinsic.
+	pub events: TransactionalVec<Event<T>>,
+}
+
+/// A claim of some content.
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct Claim<AccountId, BlockNumber> {
+	/// The account which claimed the content.
+	pub owner: AccountId,
+	/// The block in which the content was claimed.
+	pub block_number: BlockNumber,
+	/// A description of the content given by its owner, of at most `MAX_DESCRIPTION_LEN` bytes.
+	pub description: Option<Vec<u8>>,
+	/// The block at the end of which the claim expires, or `None` if it lasts until it is revoked.
+	pub expires_at: Option<BlockNumber>,
+}
+
+/// The genesis configuration of the Proof of Existence Module.
+#[derive(serde::Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct GenesisConfig<T: Config> {
+	/// The claims which already exist, by the content they claim, along with their owner. Only the
+	/// hash of the content is stored.
+	pub claims: Vec<(String, T::AccountId)>,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+	fn default() -> Self {
+		Self { claims: Vec::new() }
+	}
 }
 
 impl<T: Config> Pallet<T> {
 	/// Create a new instance of the Proof of Existence Module.
-	pub fn new() -> Self {
-		Self { claims: BTreeMap::new() }
+	pub fn new(storage: Storage) -> Self {
+		Self { storage, events: TransactionalVec::new() }
+	}
+
+	/// Set up the genesis state of the pallet. Genesis claims are made in the genesis block.
+	pub fn build_genesis(&mut self, config: &GenesisConfig<T>) -> DispatchResult {
+		let block_number = self.block_number();
+		for (content, owner) in &config.claims {
+			let claim = Self::claim_for_bytes(content.as_bytes());
+			let record =
+				Claim { owner: owner.clone(), block_number, description: None, expires_at: None };
+			self.claims().insert(&claim, &record);
+			self.claims_by_owner().insert(owner, &claim, &());
+		}
+		Ok(())
+	}
+
+	/// The key of the claim of `content`, i.e. its hash.
+	pub fn claim_for_bytes(content: &[u8]) -> ContentHash<T> {
+		T::Hashing::hash(content)
+	}
+
+	/// The current block number, kept by the system pallet in the same storage as this pallet.
+	fn block_number(&self) -> T::BlockNumber {
+		SystemPallet::<T>::new(self.storage.clone()).block_number()
 	}
 
-	pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
-		self.claims.get(claim)
+	/// The storage key of the claim of `claim`.
+	pub fn claim_key(&self, claim: &ContentHash<T>) -> Vec<u8> {
+		self.claims().key(claim)
 	}
 
-	pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-		if self.claims.contains_key(&claim) {
-			return Err("Claim already exists");
+	pub fn get_claim(&self, claim: &ContentHash<T>) -> Option<ClaimOf<T>> {
+		self.claims().get(claim)
+	}
+
+	/// The owner of `content`, e.g. the bytes of a file, and the block in which it was claimed.
+	pub fn claim_of_bytes(&self, content: &[u8]) -> Option<ClaimOf<T>> {
+		self.get_claim(&Self::claim_for_bytes(content))
+	}
+
+	/// All the claims owned by `owner`.
+	pub fn claims_of(&self, owner: &T::AccountId) -> Vec<ContentHash<T>> {
+		self.claims_by_owner().iter_prefix(owner).map(|(claim, ())| claim).collect()
+	}
+
+	/// A proof of the claim of `claim`, or that it was not claimed, against the current state root.
+	pub fn claim_proof(&self, claim: &ContentHash<T>) -> trie::Proof {
+		self.storage.prove(&self.claim_key(claim))
+	}
+
+	/// Check that a claim can be set to expire at the end of the block `expires_at`, which must be
+	/// at most `MAX_EXPIRY` blocks after the current block, and have room for another expiry.
+	fn ensure_can_expire_at(&self, expires_at: &T::BlockNumber) -> DispatchResult {
+		let horizon = self.block_number().checked_add(&T::MAX_EXPIRY);
+		if horizon.is_some_and(|horizon| *expires_at > horizon) {
+			return Err(Error::<T>::InvalidExpiry.into());
+		}
+		if self.expiries().iter_prefix(expires_at).count() >= T::MAX_EXPIRIES_PER_BLOCK as usize {
+			return Err(Error::<T>::TooManyExpiries.into());
 		}
-		self.claims.insert(claim, caller.clone());
 		Ok(())
 	}
 
-	pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-		let owner = self.get_claim(&claim).ok_or("Claim doesn't exist");
-		if owner.unwrap() != &caller {
-			return Err("Caller is not owner of claim");
+	/// Remove `claim`, whose record is `record`, returning its deposit to its owner.
+	fn remove_claim(runtime: &mut T, claim: &ContentHash<T>, record: &ClaimOf<T>) {
+		let pallet: &mut Self = runtime.pallet();
+		pallet.claims().remove(claim);
+		pallet.claims_by_owner().remove(&record.owner, claim);
+		if let Some(expires_at) = &record.expires_at {
+			pallet.expiries().remove(expires_at, claim);
 		}
-		self.claims.remove(&claim);
-		Ok(())
+		let deposit = pallet.deposits().get(claim);
+		pallet.deposits().remove(claim);
+
+		if let Some(deposit) = deposit {
+			let backing = Self::deposit_backing(runtime, &record.owner, deposit);
+			let pallet: &mut Self = runtime.pallet();
+			pallet.release_deposit(&record.owner, deposit);
+			let currency: &mut T::Currency = runtime.pallet();
+			currency.unreserve(&record.owner, backing);
+		}
+	}
+
+	/// The part of `deposit`, held for a claim of `owner`, which is still reserved.
+	///
+	/// Part of the reserved balance of `owner` may have been slashed since the deposit was
+	/// reserved. What is slashed is taken from this claim first, so releasing its deposit never
+	/// releases the deposits of the other claims of `owner`.
+	fn deposit_backing(
+		runtime: &mut T,
+		owner: &T::AccountId,
+		deposit: BalanceOf<T>,
+	) -> BalanceOf<T> {
+		let pallet: &mut Self = runtime.pallet();
+		let held = pallet.owner_deposits().get(owner).unwrap_or(Zero::zero());
+		let others = held.saturating_sub(deposit);
+		let currency: &mut T::Currency = runtime.pallet();
+		currency.reserved_balance(owner).saturating_sub(others).min(deposit)
+	}
+
+	/// Record that `amount` more is held for the claims of `who`.
+	fn hold_deposit(&mut self, who: &T::AccountId, amount: BalanceOf<T>) {
+		self.owner_deposits().mutate(who, |held| {
+			*held = Some(held.unwrap_or(Zero::zero()).saturating_add(amount));
+		});
+	}
+
+	/// Record that `amount` less is held for the claims of `who`.
+	fn release_deposit(&mut self, who: &T::AccountId, amount: BalanceOf<T>) {
+		self.owner_deposits().mutate(who, |held| {
+			*held = held.map(|held| held.saturating_sub(amount)).filter(|held| !held.is_zero());
+		});
 	}
 }
 
-pub enum Call<T: Config> {
-	CreateClaim { claim: T::Content },
-	RevokeClaim { claim: T::Content },
+#[macros::storage]
+impl<T: Config> Pallet<T> {
+	/// A map from the hash of content to its claim. Accounts can make multiple different claims,
+	/// but each claim can only have one owner.
+	type Claims = StorageMap<ContentHash<T>, ClaimOf<T>>;
+	/// The claims of every account, to list them without going through all claims.
+	type ClaimsByOwner = StorageDoubleMap<T::AccountId, ContentHash<T>, ()>;
+	/// The deposit held for each claim, which is returned to its owner when it is revoked. Claims
+	/// made at genesis hold none.
+	type Deposits = StorageMap<ContentHash<T>, BalanceOf<T>>;
+	/// The sum of the deposits held for the claims of each account.
+	type OwnerDeposits = StorageMap<T::AccountId, BalanceOf<T>>;
+	/// The claims expiring at the end of each block, which are removed when the block is finalized.
+	type Expiries = StorageDoubleMap<T::BlockNumber, ContentHash<T>, ()>;
+}
+
+#[macros::error]
+pub enum Error<T: Config> {
+	/// This content has already been claimed.
+	ClaimAlreadyExists,
+	/// This content has not been claimed.
+	ClaimDoesNotExist,
+	/// The claim is owned by another account.
+	NotClaimOwner,
+	/// The caller can not afford the claim deposit.
+	InsufficientDeposit,
+	/// The description is longer than `MAX_DESCRIPTION_LEN`.
+	DescriptionTooLong,
+	/// The expiry is not after the current block, is more than `MAX_EXPIRY` blocks after it, or
+	/// would not extend the claim.
+	InvalidExpiry,
+	/// The claim lasts until it is revoked, so it can not be renewed.
+	ClaimDoesNotExpire,
+	/// `MAX_EXPIRIES_PER_BLOCK` claims already expire in the block.
+	TooManyExpiries,
+}
+
+#[macros::event]
+#[allow(clippy::enum_variant_names)]
+pub enum Event<T: Config> {
+	/// `owner` claimed `claim`.
+	ClaimCreated { owner: T::AccountId, claim: ContentHash<T> },
+	/// `owner` revoked their claim of `claim`.
+	ClaimRevoked { owner: T::AccountId, claim: ContentHash<T> },
+	/// The ownership of `claim` was handed from `from` to `to`.
+	ClaimTransferred { from: T::AccountId, to: T::AccountId, claim: ContentHash<T> },
+	/// The claim of `owner` of `claim` expired, returning its deposit.
+	ClaimExpired { owner: T::AccountId, claim: ContentHash<T> },
+	/// `owner` extended their claim of `claim` until the end of the block `expires_at`.
+	ClaimRenewed { owner: T::AccountId, claim: ContentHash<T>, expires_at: T::BlockNumber },
 }
 
-impl<T: Config> crate::support::Dispatch for Pallet<T> {
-	type Caller = T::AccountId;
-	type Call = Call<T>;
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Claim the content hashed to `claim` on behalf of the caller, reserving the claim deposit from
+	/// their balance. See `claim_for_bytes` for the hash of some content.
+	///
+	/// The claim lasts until it is revoked, or if `expires_at` is given, until the end of that
+	/// block, which must come after the current block, by at most `MAX_EXPIRY` blocks.
+	///
+	/// Failing because the content is already claimed only uses the weight of checking the claim.
+	#[weight(20_000)]
+	pub fn create_claim(
+		runtime: &mut T,
+		origin: T::RuntimeOrigin,
+		claim: ContentHash<T>,
+		description: Option<Vec<u8>>,
+		expires_at: Option<T::BlockNumber>,
+	) -> DispatchResultWithPostInfo {
+		let caller = ensure_signed(origin)?;
+		let pallet: &mut Self = runtime.pallet();
+		if pallet.claims().contains_key(&claim) {
+			return Err(Error::<T>::ClaimAlreadyExists.with_weight(5_000));
+		}
+		if description.as_ref().is_some_and(|d| d.len() > T::MAX_DESCRIPTION_LEN as usize) {
+			return Err(Error::<T>::DescriptionTooLong.into());
+		}
+		if let Some(expires_at) = &expires_at {
+			if *expires_at <= pallet.block_number() {
+				return Err(Error::<T>::InvalidExpiry.into());
+			}
+			pallet.ensure_can_expire_at(expires_at)?;
+		}
 
-	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult {
-		match call {
-			Call::CreateClaim { claim } => self.create_claim(caller, claim)?,
-			Call::RevokeClaim { claim } => self.revoke_claim(caller, claim)?,
+		let currency: &mut T::Currency = runtime.pallet();
+		if !currency.can_reserve(&caller, T::CLAIM_DEPOSIT) {
+			return Err(Error::<T>::InsufficientDeposit.into());
 		}
+		currency.reserve(&caller, T::CLAIM_DEPOSIT)?;
+
+		let pallet: &mut Self = runtime.pallet();
+		let block_number = pallet.block_number();
+		let record = Claim { owner: caller.clone(), block_number, description, expires_at };
+		pallet.claims().insert(&claim, &record);
+		pallet.claims_by_owner().insert(&caller, &claim, &());
+		pallet.deposits().insert(&claim, &T::CLAIM_DEPOSIT);
+		pallet.hold_deposit(&caller, T::CLAIM_DEPOSIT);
+		if let Some(expires_at) = &expires_at {
+			pallet.expiries().insert(expires_at, &claim, &());
+		}
+		pallet.deposit_event(Event::ClaimCreated { owner: caller, claim });
+		Ok(().into())
+	}
+
+	/// Revoke `claim`, which must be owned by the caller, returning its deposit.
+	#[weight(10_000)]
+	pub fn revoke_claim(
+		runtime: &mut T,
+		origin: T::RuntimeOrigin,
+		claim: ContentHash<T>,
+	) -> DispatchResult {
+		let caller = ensure_signed(origin)?;
+		let pallet: &mut Self = runtime.pallet();
+		let record = pallet.get_claim(&claim).ok_or(Error::<T>::ClaimDoesNotExist)?;
+		if record.owner != caller {
+			return Err(Error::<T>::NotClaimOwner.into());
+		}
+		Self::remove_claim(runtime, &claim, &record);
+		let pallet: &mut Self = runtime.pallet();
+		pallet.deposit_event(Event::ClaimRevoked { owner: caller, claim });
 		Ok(())
 	}
+
+	/// Hand `claim`, which must be owned by the caller, over to `new_owner`.
+	///
+	/// The deposit of the claim moves along with it, and is returned to `new_owner` when they
+	/// revoke the claim. If part of it was slashed in the meantime, only what is left moves.
+	/// `new_owner` must be an existing account.
+	#[weight(15_000)]
+	pub fn transfer_claim(
+		runtime: &mut T,
+		origin: T::RuntimeOrigin,
+		claim: ContentHash<T>,
+		new_owner: T::AccountId,
+	) -> DispatchResult {
+		let caller = ensure_signed(origin)?;
+		let pallet: &mut Self = runtime.pallet();
+		let mut record = pallet.get_claim(&claim).ok_or(Error::<T>::ClaimDoesNotExist)?;
+		if record.owner != caller {
+			return Err(Error::<T>::NotClaimOwner.into());
+		}
+
+		if let Some(deposit) = pallet.deposits().get(&claim) {
+			let backing = Self::deposit_backing(runtime, &caller, deposit);
+			let currency: &mut T::Currency = runtime.pallet();
+			let remainder = currency.repatriate_reserved(&caller, &new_owner, backing)?;
+			// The new owner must not get back more than was moved when revoking the claim.
+			let moved = backing.saturating_sub(remainder);
+			let pallet: &mut Self = runtime.pallet();
+			pallet.deposits().insert(&claim, &moved);
+			pallet.release_deposit(&caller, deposit);
+			pallet.hold_deposit(&new_owner, moved);
+		}
+
+		let pallet: &mut Self = runtime.pallet();
+		record.owner = new_owner.clone();
+		pallet.claims().insert(&claim, &record);
+		pallet.claims_by_owner().remove(&caller, &claim);
+		pallet.claims_by_owner().insert(&new_owner, &claim, &());
+		pallet.deposit_event(Event::ClaimTransferred { from: caller, to: new_owner, claim });
+		Ok(())
+	}
+
+	/// Extend `claim`, which must be owned by the caller and expire, until the end of the block
+	/// `expires_at`, which must come after its current expiry, by at most `MAX_EXPIRY` blocks from
+	/// the current block.
+	#[weight(10_000)]
+	pub fn renew_claim(
+		&mut self,
+		origin: T::RuntimeOrigin,
+		claim: ContentHash<T>,
+		expires_at: T::BlockNumber,
+	) -> DispatchResult {
+		let caller = ensure_signed(origin)?;
+		let mut record = self.get_claim(&claim).ok_or(Error::<T>::ClaimDoesNotExist)?;
+		if record.owner != caller {
+			return Err(Error::<T>::NotClaimOwner.into());
+		}
+		let current_expiry = record.expires_at.ok_or(Error::<T>::ClaimDoesNotExpire)?;
+		if expires_at <= current_expiry {
+			return Err(Error::<T>::InvalidExpiry.into());
+		}
+		self.ensure_can_expire_at(&expires_at)?;
+
+		record.expires_at = Some(expires_at);
+		self.claims().insert(&claim, &record);
+		self.expiries().remove(&current_expiry, &claim);
+		self.expiries().insert(&expires_at, &claim, &());
+		self.deposit_event(Event::ClaimRenewed { owner: caller, claim, expires_at });
+		Ok(())
+	}
+}
+
+/// Claims expire at the end of their expiry block, returning their deposit to their owner. The
+/// weight of removing them is accounted for when the block is initialized, and is bounded by
+/// `MAX_EXPIRIES_PER_BLOCK`.
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {
+	fn on_initialize(runtime: &mut T, n: T::BlockNumber) -> Weight {
+		let pallet: &mut Self = runtime.pallet();
+		let expiring = pallet.expiries().iter_prefix(&n).count() as Weight;
+		expiring.saturating_mul(EXPIRE_CLAIM_WEIGHT)
+	}
+
+	fn on_finalize(runtime: &mut T, n: T::BlockNumber) {
+		let pallet: &mut Self = runtime.pallet();
+		for (claim, ()) in pallet.expiries().iter_prefix(&n) {
+			let pallet: &mut Self = runtime.pallet();
+			// Removing a claim takes it out of the expiry queue, so every queued claim exists.
+			let Some(record) = pallet.get_claim(&claim) else { continue };
+			Self::remove_claim(runtime, &claim, &record);
+			let pallet: &mut Self = runtime.pallet();
+			pallet.deposit_event(Event::ClaimExpired { owner: record.owner, claim });
+		}
+	}
+}
+
+impl<T: Config> Debug for Pallet<T>
+where
+	T::AccountId: Debug,
+	T::BlockNumber: Debug,
+	BalanceOf<T>: Debug,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("Pallet")
+			.field("claims", &BTreeMap::from_iter(self.claims().iter()))
+			.field("deposits", &BTreeMap::from_iter(self.deposits().iter()))
+			.field(
+				"expiries",
+				&Vec::from_iter(self.expiries().iter().map(|(n, claim, ())| (n, claim))),
+			)
+			.field("events", &self.events)
+			.finish()
+	}
 }
 
 #[cfg(test)]
 mod test {
-	struct TestConfig;
+	use crate::{
+		balances,
+		crypto::Keyring,
+		support::{Hooks, RawOrigin, ReservableCurrency, WithPostDispatchInfo},
+		types::Runtime,
+	};
+
+	type Pallet = super::Pallet<Runtime>;
 
-	impl super::Config for TestConfig {
-		type Content = String;
+	#[test]
+	fn basic_proof_of_existence() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let bob = Keyring::Bob.to_account_id();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+		runtime.balances.set_balance(&bob, 100_000).unwrap();
+		let claim = Pallet::claim_for_bytes(b"Hello, world!");
+
+		assert_eq!(runtime.proof_of_existence.get_claim(&claim), None);
+		assert_eq!(
+			Pallet::create_claim(&mut runtime, RawOrigin::Signed(alice), claim, None, None),
+			Ok(().into())
+		);
+		let owner = runtime.proof_of_existence.get_claim(&claim).map(|claim| claim.owner);
+		assert_eq!(owner, Some(alice));
+		assert_eq!(
+			Pallet::create_claim(&mut runtime, RawOrigin::Signed(bob), claim, None, None),
+			Err(super::Error::<Runtime>::ClaimAlreadyExists.with_weight(5_000))
+		);
+		assert_eq!(
+			Pallet::revoke_claim(&mut runtime, RawOrigin::Signed(bob), claim),
+			Err(super::Error::<Runtime>::NotClaimOwner.into())
+		);
+		assert_eq!(Pallet::revoke_claim(&mut runtime, RawOrigin::Signed(alice), claim), Ok(()));
+		assert_eq!(
+			Pallet::create_claim(&mut runtime, RawOrigin::Signed(bob), claim, None, None),
+			Ok(().into())
+		);
 	}
 
-	impl crate::SystemConfig for TestConfig {
-		type AccountId = String;
-		type BlockNumber = u32;
-		type Nonce = u32;
+	#[test]
+	fn claims_hold_a_deposit() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let claim = Pallet::claim_for_bytes(b"Hello, world!");
+
+		// The deposit can not reap the claimant.
+		runtime.balances.set_balance(&alice, 10_500).unwrap();
+		assert_eq!(
+			Pallet::create_claim(&mut runtime, RawOrigin::Signed(alice), claim, None, None),
+			Err(super::Error::<Runtime>::InsufficientDeposit.into())
+		);
+
+		runtime.balances.set_balance(&alice, 15_000).unwrap();
+		assert_eq!(
+			Pallet::create_claim(&mut runtime, RawOrigin::Signed(alice), claim, None, None),
+			Ok(().into())
+		);
+		assert_eq!(runtime.balances.balance(&alice), 5_000);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10_000);
+		assert_eq!(
+			runtime.balances.take_events(),
+			vec![balances::Event::Reserved { who: alice, amount: 10_000 }]
+		);
+
+		// Revoking the claim returns its deposit.
+		assert_eq!(Pallet::revoke_claim(&mut runtime, RawOrigin::Signed(alice), claim), Ok(()));
+		assert_eq!(runtime.balances.balance(&alice), 15_000);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		runtime.balances.assert_total_issuance();
 	}
 
 	#[test]
-	fn basic_proof_of_existence() {
-		let mut poe = super::Pallet::<TestConfig>::new();
-		assert_eq!(poe.get_claim(&"Hello, world!".to_string()), None);
-		assert_eq!(poe.create_claim("alice".to_string(), "Hello, world!".to_string()), Ok(()));
-		assert_eq!(poe.get_claim(&"Hello, world!".to_string()), Some(&"alice".to_string()));
+	fn claims_are_found_by_their_content() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+		runtime.system.inc_block_number();
+		runtime.system.inc_block_number();
+
+		// Only the hash of the content is claimed.
+		let content = b"The contents of a file";
+		let claim = Pallet::claim_for_bytes(content);
+		assert_eq!(claim, crate::crypto::blake2_256(content));
+		assert_eq!(
+			Pallet::create_claim(&mut runtime, RawOrigin::Signed(alice), claim, None, None),
+			Ok(().into())
+		);
+
+		let expected =
+			super::Claim { owner: alice, block_number: 2, description: None, expires_at: None };
+		assert_eq!(runtime.proof_of_existence.claim_of_bytes(content), Some(expected));
+		assert_eq!(runtime.proof_of_existence.claim_of_bytes(b"Other contents"), None);
+	}
+
+	#[test]
+	fn claims_can_be_described_and_transferred() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let bob = Keyring::Bob.to_account_id();
+		let charlie = Keyring::Charlie.to_account_id();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+		runtime.balances.set_balance(&bob, 100_000).unwrap();
+		let [first, second] =
+			["first", "second"].map(|content| Pallet::claim_for_bytes(content.as_bytes()));
+
+		// Descriptions are limited in length.
+		let description = vec![b'a'; 257];
+		assert_eq!(
+			Pallet::create_claim(
+				&mut runtime,
+				RawOrigin::Signed(alice),
+				first,
+				Some(description),
+				None
+			),
+			Err(super::Error::<Runtime>::DescriptionTooLong.into())
+		);
+		let description = Some(b"The first file".to_vec());
+		for (claim, description) in [(first, description.clone()), (second, None)] {
+			let result = Pallet::create_claim(
+				&mut runtime,
+				RawOrigin::Signed(alice),
+				claim,
+				description,
+				None,
+			);
+			assert_eq!(result, Ok(().into()));
+		}
+		// Claims are listed by their hash.
+		let mut claims = vec![first, second];
+		claims.sort();
+		assert_eq!(runtime.proof_of_existence.claims_of(&alice), claims);
+
+		// Only the owner can transfer a claim, and only to an existing account.
+		let transfer = |runtime: &mut Runtime, who, to| {
+			Pallet::transfer_claim(runtime, RawOrigin::Signed(who), first, to)
+		};
+		assert_eq!(
+			transfer(&mut runtime, bob, bob),
+			Err(super::Error::<Runtime>::NotClaimOwner.into())
+		);
+		assert_eq!(
+			transfer(&mut runtime, alice, charlie),
+			Err(balances::Error::<Runtime>::DeadAccount.into())
+		);
+
+		// The claim keeps its record, and takes its deposit along to the new owner.
+		assert_eq!(transfer(&mut runtime, alice, bob), Ok(()));
+		let expected = super::Claim { owner: bob, block_number: 0, description, expires_at: None };
+		assert_eq!(runtime.proof_of_existence.get_claim(&first), Some(expected));
+		assert_eq!(runtime.proof_of_existence.claims_of(&alice), vec![second]);
+		assert_eq!(runtime.proof_of_existence.claims_of(&bob), vec![first]);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10_000);
+		assert_eq!(runtime.balances.reserved_balance(&bob), 10_000);
+		assert_eq!(
+			runtime.proof_of_existence.take_events().last(),
+			Some(&super::Event::ClaimTransferred { from: alice, to: bob, claim: first })
+		);
+
+		// The new owner gets the deposit back when revoking the claim.
+		assert_eq!(Pallet::revoke_claim(&mut runtime, RawOrigin::Signed(bob), first), Ok(()));
+		assert_eq!(runtime.balances.balance(&bob), 110_000);
+		assert!(runtime.proof_of_existence.claims_of(&bob).is_empty());
+		runtime.balances.assert_total_issuance();
+	}
+
+	#[test]
+	fn transferred_claims_only_carry_what_is_left_of_their_deposit() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let bob = Keyring::Bob.to_account_id();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+		runtime.balances.set_balance(&bob, 100_000).unwrap();
+		let claim = Pallet::claim_for_bytes(b"Hello, world!");
 		assert_eq!(
-			poe.create_claim("bob".to_string(), "Hello, world!".to_string()),
-			Err("Claim already exists")
+			Pallet::create_claim(&mut runtime, RawOrigin::Signed(alice), claim, None, None),
+			Ok(().into())
+		);
+
+		// Part of the deposit is slashed before the claim is transferred.
+		assert_eq!(runtime.balances.slash_reserved(&alice, 4_000), 0);
+		assert_eq!(
+			Pallet::transfer_claim(&mut runtime, RawOrigin::Signed(alice), claim, bob),
+			Ok(())
+		);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		assert_eq!(runtime.balances.reserved_balance(&bob), 6_000);
+
+		// Revoking the claim only returns what was moved, and no reserve of bob is taken over.
+		runtime.balances.reserve(&bob, 1_000).unwrap();
+		assert_eq!(Pallet::revoke_claim(&mut runtime, RawOrigin::Signed(bob), claim), Ok(()));
+		assert_eq!(runtime.balances.reserved_balance(&bob), 1_000);
+		assert_eq!(runtime.balances.balance(&bob), 105_000);
+		runtime.balances.assert_total_issuance();
+	}
+
+	#[test]
+	fn revoking_a_slashed_claim_keeps_the_deposits_of_other_claims() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+		let [first, second] =
+			["first", "second"].map(|content| Pallet::claim_for_bytes(content.as_bytes()));
+		for claim in [first, second] {
+			let result =
+				Pallet::create_claim(&mut runtime, RawOrigin::Signed(alice), claim, None, None);
+			assert_eq!(result, Ok(().into()));
+		}
+
+		// What is slashed is taken from the deposit of the claim which is revoked first.
+		assert_eq!(runtime.balances.slash_reserved(&alice, 4_000), 0);
+		assert_eq!(Pallet::revoke_claim(&mut runtime, RawOrigin::Signed(alice), first), Ok(()));
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10_000);
+		assert_eq!(runtime.balances.balance(&alice), 86_000);
+
+		assert_eq!(Pallet::revoke_claim(&mut runtime, RawOrigin::Signed(alice), second), Ok(()));
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		assert_eq!(runtime.balances.balance(&alice), 96_000);
+		runtime.balances.assert_total_issuance();
+	}
+
+	#[test]
+	fn claims_expire_unless_renewed() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		let bob = Keyring::Bob.to_account_id();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+		runtime.system.inc_block_number();
+		let [first, second, third] =
+			["first", "second", "third"].map(|content| Pallet::claim_for_bytes(content.as_bytes()));
+		let create = |runtime: &mut Runtime, claim, expires_at| {
+			Pallet::create_claim(runtime, RawOrigin::Signed(alice), claim, None, expires_at)
+		};
+
+		// Claims must expire after the current block.
+		assert_eq!(
+			create(&mut runtime, first, Some(1)),
+			Err(super::Error::<Runtime>::InvalidExpiry.into())
+		);
+		assert_eq!(create(&mut runtime, first, Some(2)), Ok(().into()));
+		assert_eq!(create(&mut runtime, second, Some(2)), Ok(().into()));
+		assert_eq!(create(&mut runtime, third, None), Ok(().into()));
+
+		// Only the owner can renew a claim, and only to extend it.
+		let mut renew = |who, claim, expires_at| {
+			runtime
+				.proof_of_existence
+				.renew_claim(RawOrigin::Signed(who), claim, expires_at)
+		};
+		assert_eq!(renew(bob, second, 3), Err(super::Error::<Runtime>::NotClaimOwner.into()));
+		assert_eq!(renew(alice, second, 2), Err(super::Error::<Runtime>::InvalidExpiry.into()));
+		assert_eq!(renew(alice, third, 3), Err(super::Error::<Runtime>::ClaimDoesNotExpire.into()));
+		assert_eq!(renew(alice, second, 3), Ok(()));
+		runtime.proof_of_existence.take_events();
+
+		// Claims expire at the end of their expiry block, returning their deposit. The weight of
+		// removing them is accounted for at the start of the block.
+		assert_eq!(Pallet::on_initialize(&mut runtime, 2), 10_000);
+		Pallet::on_finalize(&mut runtime, 2);
+		assert_eq!(runtime.proof_of_existence.get_claim(&first), None);
+		let mut claims = vec![second, third];
+		claims.sort();
+		assert_eq!(runtime.proof_of_existence.claims_of(&alice), claims);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 20_000);
+		assert_eq!(
+			runtime.proof_of_existence.take_events(),
+			vec![super::Event::ClaimExpired { owner: alice, claim: first }]
+		);
+
+		// Revoked claims no longer expire.
+		assert_eq!(Pallet::revoke_claim(&mut runtime, RawOrigin::Signed(alice), second), Ok(()));
+		runtime.proof_of_existence.take_events();
+		Pallet::on_finalize(&mut runtime, 3);
+		assert!(runtime.proof_of_existence.take_events().is_empty());
+		assert_eq!(runtime.proof_of_existence.claims_of(&alice), vec![third]);
+		runtime.balances.assert_total_issuance();
+	}
+
+	#[test]
+	fn expiries_are_limited() {
+		let mut runtime = Runtime::new();
+		let alice = Keyring::Alice.to_account_id();
+		runtime.balances.set_balance(&alice, 1_000_000).unwrap();
+		let max_expiry = <Runtime as super::Config>::MAX_EXPIRY;
+		let max_expiries = <Runtime as super::Config>::MAX_EXPIRIES_PER_BLOCK;
+		let create = |runtime: &mut Runtime, content: String, expires_at| {
+			let claim = Pallet::claim_for_bytes(content.as_bytes());
+			Pallet::create_claim(runtime, RawOrigin::Signed(alice), claim, None, Some(expires_at))
+		};
+
+		// Claims can not be set to expire too far ahead...
+		let result = create(&mut runtime, "far".to_string(), max_expiry + 1);
+		assert_eq!(result, Err(super::Error::<Runtime>::InvalidExpiry.into()));
+		assert_eq!(create(&mut runtime, "far".to_string(), max_expiry), Ok(().into()));
+		let far = Pallet::claim_for_bytes(b"far");
+		let result = Pallet::renew_claim(
+			&mut runtime.proof_of_existence,
+			RawOrigin::Signed(alice),
+			far,
+			max_expiry + 1,
+		);
+		assert_eq!(result, Err(super::Error::<Runtime>::InvalidExpiry.into()));
+
+		// ...nor pile up in the same block, whose expiries have a bounded weight.
+		for i in 0..max_expiries {
+			assert_eq!(create(&mut runtime, i.to_string(), 2), Ok(().into()));
+		}
+		let result = create(&mut runtime, "one too many".to_string(), 2);
+		assert_eq!(result, Err(super::Error::<Runtime>::TooManyExpiries.into()));
+		assert_eq!(create(&mut runtime, "one too many".to_string(), 1), Ok(().into()));
+		let one_too_many = Pallet::claim_for_bytes(b"one too many");
+		let result = Pallet::renew_claim(
+			&mut runtime.proof_of_existence,
+			RawOrigin::Signed(alice),
+			one_too_many,
+			2,
 		);
-		assert_eq!(poe.revoke_claim("alice".to_string(), "Hello, world!".to_string()), Ok(()));
-		assert_eq!(poe.create_claim("bob".to_string(), "Hello, world!".to_string()), Ok(()));
+		assert_eq!(result, Err(super::Error::<Runtime>::TooManyExpiries.into()));
+		assert_eq!(Pallet::on_initialize(&mut runtime, 2), max_expiries as u64 * 10_000);
 	}
 }
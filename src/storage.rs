@@ -0,0 +1,376 @@
+//! The key-value storage shared by all the pallets of a runtime.
+//!
+//! Pallets keep their state in the same `Storage`, under keys starting with a prefix unique to each
+//! of their storage items, followed by the encoded key of the entry. Values are encoded with the
+//! codec. The whole storage is committed to by the root of a Merkle trie, see `crate::trie`.
+//!
+//! Pallets do not use the `Storage` directly, but declare typed storage items with
+//! `#[macros::storage]`: a `StorageValue`, `StorageMap` or `StorageDoubleMap`, with a key prefix
+//! given by `prefix`.
+//!
+//! Any entry of the storage, or its absence, can be proven against that root. A light client only
+//! trusting a header can check such a proof with `verify_proof`, without access to the storage.
+
+use crate::{
+	codec::{self, Decode, Encode},
+	crypto::{H256, blake2_256},
+	support::{Transactional, TransactionalMap},
+	trie,
+};
+use core::marker::PhantomData;
+use std::{cell::RefCell, rc::Rc};
+
+/// A handle to the storage of a runtime.
+///
+/// Cloning a `Storage` gives another handle to the same storage, which is how every pallet of a
+/// runtime gets access to it.
+#[derive(Clone, Default)]
+pub struct Storage(Rc<RefCell<TransactionalMap<Vec<u8>, Vec<u8>>>>);
+
+impl Storage {
+	/// Create a new, empty storage.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The key of the entry for `key` in the storage item with the given prefix.
+	pub fn key(prefix: &[u8], key: &impl Encode) -> Vec<u8> {
+		let mut full_key = prefix.to_vec();
+		key.encode_to(&mut full_key);
+		full_key
+	}
+
+	/// The value stored at `key`, if any.
+	pub fn get<V: Decode>(&self, key: &[u8]) -> Option<V> {
+		let storage = self.0.borrow();
+		let value = storage.get(key)?;
+		Some(V::decode_all(value).expect("storage values are always valid encodings"))
+	}
+
+	pub fn contains_key(&self, key: &[u8]) -> bool {
+		self.0.borrow().contains_key(key)
+	}
+
+	pub fn insert(&self, key: &[u8], value: &impl Encode) {
+		self.0.borrow_mut().insert(key.to_vec(), value.encode());
+	}
+
+	pub fn remove(&self, key: &[u8]) {
+		self.0.borrow_mut().remove(key.to_vec());
+	}
+
+	/// All the entries with keys starting with `prefix`, with the prefix removed from their keys,
+	/// sorted by key.
+	pub fn iter_prefix<K: Decode, V: Decode>(&self, prefix: &[u8]) -> Vec<(K, V)> {
+		self.0
+			.borrow()
+			.iter()
+			.filter_map(|(key, value)| Some((key.strip_prefix(prefix)?, value)))
+			.map(|(key, value)| {
+				let key = K::decode_all(key).expect("storage keys are always valid encodings");
+				let value =
+					V::decode_all(value).expect("storage values are always valid encodings");
+				(key, value)
+			})
+			.collect()
+	}
+
+	/// Every entry of the storage, with its raw key and value, sorted by key.
+	pub fn entries(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+		let storage = self.0.borrow();
+		storage.iter().map(|(key, value)| (key.clone(), value.clone())).collect()
+	}
+
+	/// Replace every entry with the given raw keys and values, e.g. when restoring the storage from
+	/// a database.
+	pub fn restore(&self, entries: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>) {
+		let mut storage = self.0.borrow_mut();
+		*storage = TransactionalMap::new();
+		for (key, value) in entries {
+			storage.insert(key, value);
+		}
+	}
+
+	/// The root of the trie holding the current state of the storage.
+	pub fn root(&self) -> H256 {
+		let storage = self.0.borrow();
+		trie::root(storage.iter().map(|(key, value)| (key.as_slice(), value.as_slice())))
+	}
+
+	/// A proof of the value stored at `key`, or of its absence, against the current root.
+	pub fn prove(&self, key: &[u8]) -> trie::Proof {
+		let storage = self.0.borrow();
+		trie::prove(storage.iter().map(|(key, value)| (key.as_slice(), value.as_slice())), key)
+	}
+}
+
+/// The key prefix of the storage item named `item`, of the pallet named `pallet` in the runtime.
+///
+/// Both names are hashed, so the prefixes of all the items have the same length, and all the items
+/// of a pallet share the first half of their prefix.
+pub fn prefix(pallet: &str, item: &str) -> Vec<u8> {
+	[&blake2_256(pallet.as_bytes())[..16], &blake2_256(item.as_bytes())[..16]].concat()
+}
+
+/// A storage item holding a single value of type `V`.
+pub struct StorageValue<V> {
+	storage: Storage,
+	prefix: Vec<u8>,
+	_value: PhantomData<V>,
+}
+
+impl<V: Encode + Decode> StorageValue<V> {
+	/// The storage item with the given prefix in `storage`.
+	pub fn new(storage: Storage, prefix: Vec<u8>) -> Self {
+		Self { storage, prefix, _value: PhantomData }
+	}
+
+	pub fn get(&self) -> Option<V> {
+		self.storage.get(&self.prefix)
+	}
+
+	pub fn insert(&self, value: &V) {
+		self.storage.insert(&self.prefix, value);
+	}
+}
+
+/// A storage item mapping keys of type `K` to values of type `V`.
+pub struct StorageMap<K, V> {
+	storage: Storage,
+	prefix: Vec<u8>,
+	_entries: PhantomData<(K, V)>,
+}
+
+impl<K: Encode + Decode, V: Encode + Decode> StorageMap<K, V> {
+	/// The storage item with the given prefix in `storage`.
+	pub fn new(storage: Storage, prefix: Vec<u8>) -> Self {
+		Self { storage, prefix, _entries: PhantomData }
+	}
+
+	/// The storage key of the value of `key`.
+	pub fn key(&self, key: &K) -> Vec<u8> {
+		Storage::key(&self.prefix, key)
+	}
+
+	pub fn get(&self, key: &K) -> Option<V> {
+		self.storage.get(&self.key(key))
+	}
+
+	pub fn contains_key(&self, key: &K) -> bool {
+		self.storage.contains_key(&self.key(key))
+	}
+
+	pub fn insert(&self, key: &K, value: &V) {
+		self.storage.insert(&self.key(key), value);
+	}
+
+	pub fn remove(&self, key: &K) {
+		self.storage.remove(&self.key(key));
+	}
+
+	/// Mutate the value of `key` in place, removing it if it is set to `None`.
+	pub fn mutate<R>(&self, key: &K, f: impl FnOnce(&mut Option<V>) -> R) -> R {
+		match self.try_mutate(key, |value| Ok::<_, core::convert::Infallible>(f(value))) {
+			Ok(result) => result,
+		}
+	}
+
+	/// Mutate the value of `key` in place, removing it if it is set to `None`. Nothing is written
+	/// if `f` fails.
+	pub fn try_mutate<R, E>(
+		&self,
+		key: &K,
+		f: impl FnOnce(&mut Option<V>) -> Result<R, E>,
+	) -> Result<R, E> {
+		let mut value = self.get(key);
+		let result = f(&mut value)?;
+		match value {
+			Some(value) => self.insert(key, &value),
+			None => self.remove(key),
+		}
+		Ok(result)
+	}
+
+	/// All the entries of the map, sorted by their encoded key.
+	pub fn iter(&self) -> impl Iterator<Item = (K, V)> + use<K, V> {
+		self.storage.iter_prefix(&self.prefix).into_iter()
+	}
+}
+
+/// A storage item mapping pairs of keys of types `K1` and `K2` to values of type `V`.
+///
+/// Entries are stored under the first key followed by the second, so the entries sharing a first
+/// key can be iterated over together.
+pub struct StorageDoubleMap<K1, K2, V> {
+	storage: Storage,
+	prefix: Vec<u8>,
+	_entries: PhantomData<(K1, K2, V)>,
+}
+
+impl<K1: Encode + Decode, K2: Encode + Decode, V: Encode + Decode> StorageDoubleMap<K1, K2, V> {
+	/// The storage item with the given prefix in `storage`.
+	pub fn new(storage: Storage, prefix: Vec<u8>) -> Self {
+		Self { storage, prefix, _entries: PhantomData }
+	}
+
+	/// The storage key of the value of `(key1, key2)`.
+	pub fn key(&self, key1: &K1, key2: &K2) -> Vec<u8> {
+		Storage::key(&self.prefix, &(key1, key2))
+	}
+
+	pub fn insert(&self, key1: &K1, key2: &K2, value: &V) {
+		self.storage.insert(&self.key(key1, key2), value);
+	}
+
+	pub fn remove(&self, key1: &K1, key2: &K2) {
+		self.storage.remove(&self.key(key1, key2));
+	}
+
+	/// All the entries of the map, sorted by their encoded keys.
+	pub fn iter(&self) -> impl Iterator<Item = (K1, K2, V)> + use<K1, K2, V> {
+		let entries = self.storage.iter_prefix::<(K1, K2), V>(&self.prefix);
+		entries.into_iter().map(|((key1, key2), value)| (key1, key2, value))
+	}
+
+	/// All the entries with the first key `key1`, sorted by their encoded second key.
+	pub fn iter_prefix(&self, key1: &K1) -> impl Iterator<Item = (K2, V)> + use<K1, K2, V> {
+		self.storage.iter_prefix(&Storage::key(&self.prefix, key1)).into_iter()
+	}
+}
+
+/// Why a storage proof was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofError {
+	/// The proof does not match the state root.
+	InvalidProof,
+	/// The proven value is not a valid encoding of the expected type.
+	InvalidValue(codec::Error),
+}
+
+/// Check `proof` against a state root, usually taken from a trusted header, returning the value
+/// stored at `key` in that state, or `None` if there is no such value.
+pub fn verify_proof<V: Decode>(
+	state_root: &H256,
+	key: &[u8],
+	proof: &trie::Proof,
+) -> Result<Option<V>, ProofError> {
+	if !trie::verify(state_root, key, proof) {
+		return Err(ProofError::InvalidProof);
+	}
+	proof
+		.value
+		.as_deref()
+		.map(V::decode_all)
+		.transpose()
+		.map_err(ProofError::InvalidValue)
+}
+
+impl Transactional for Storage {
+	fn start_transaction(&mut self) {
+		self.0.borrow_mut().start_transaction();
+	}
+
+	fn commit_transaction(&mut self) {
+		self.0.borrow_mut().commit_transaction();
+	}
+
+	fn rollback_transaction(&mut self) {
+		self.0.borrow_mut().rollback_transaction();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		ProofError, Storage, StorageDoubleMap, StorageMap, StorageValue, prefix, verify_proof,
+	};
+	use crate::support::Transactional;
+
+	#[test]
+	fn handles_share_the_storage() {
+		let mut storage = Storage::new();
+		let other = storage.clone();
+		let empty_root = storage.root();
+
+		storage.start_transaction();
+		other.insert(&Storage::key(b"Numbers/", &1u32), &10u64);
+		other.insert(&Storage::key(b"Numbers/", &2u32), &20u64);
+		other.insert(b"Other", &true);
+		assert_eq!(storage.get::<u64>(&Storage::key(b"Numbers/", &1u32)), Some(10));
+		assert_eq!(storage.iter_prefix::<u32, u64>(b"Numbers/"), vec![(1, 10), (2, 20)]);
+		assert_ne!(storage.root(), empty_root);
+
+		storage.rollback_transaction();
+		assert!(!other.contains_key(b"Other"));
+		assert_eq!(other.root(), empty_root);
+	}
+
+	#[test]
+	fn storage_items() {
+		let storage = Storage::new();
+		let value = StorageValue::<u32>::new(storage.clone(), prefix("Pallet", "Value"));
+		let map = StorageMap::<u32, u64>::new(storage.clone(), prefix("Pallet", "Map"));
+		let double_map =
+			StorageDoubleMap::<u32, u32, bool>::new(storage.clone(), prefix("Pallet", "DoubleMap"));
+
+		assert_eq!(value.get(), None);
+		value.insert(&2);
+		assert_eq!(value.get(), Some(2));
+
+		map.insert(&2, &20);
+		map.insert(&1, &10);
+		map.mutate(&2, |value| *value = value.map(|value| value + 1));
+		assert_eq!(map.get(&2), Some(21));
+		// Nothing is written if the mutation fails.
+		assert_eq!(map.try_mutate(&3, |value| value.replace(30).ok_or(())), Err(()));
+		assert!(!map.contains_key(&3));
+		map.mutate(&2, |value| *value = None);
+		assert!(!map.contains_key(&2));
+		map.insert(&2, &20);
+		assert_eq!(map.iter().collect::<Vec<_>>(), vec![(1, 10), (2, 20)]);
+		map.remove(&1);
+		assert_eq!(map.get(&1), None);
+
+		double_map.insert(&1, &2, &true);
+		double_map.insert(&1, &1, &false);
+		double_map.insert(&2, &1, &true);
+		assert_eq!(double_map.iter_prefix(&1).collect::<Vec<_>>(), vec![(1, false), (2, true)]);
+		double_map.remove(&1, &1);
+		assert_eq!(double_map.iter().collect::<Vec<_>>(), vec![(1, 2, true), (2, 1, true)]);
+
+		// Every item has its own prefix, so they never overwrite each other.
+		assert_eq!(value.get(), Some(2));
+		assert_eq!(storage.iter_prefix::<u32, u64>(&prefix("Pallet", "Map")), vec![(2, 20)]);
+		assert_ne!(prefix("Pallet", "Map"), prefix("Other", "Map"));
+	}
+
+	#[test]
+	fn proofs_are_checked_against_the_root() {
+		let storage = Storage::new();
+		let key = |n: u32| Storage::key(b"Numbers/", &n);
+		for n in 0..10u32 {
+			storage.insert(&key(n), &(n as u64 * 10));
+		}
+		let root = storage.root();
+
+		assert_eq!(verify_proof::<u64>(&root, &key(3), &storage.prove(&key(3))), Ok(Some(30)));
+		assert_eq!(verify_proof::<u64>(&root, &key(10), &storage.prove(&key(10))), Ok(None));
+		// A proof for one key does not prove anything about another.
+		assert_eq!(
+			verify_proof::<u64>(&root, &key(4), &storage.prove(&key(3))),
+			Err(ProofError::InvalidProof)
+		);
+		// The value must decode as the expected type.
+		assert!(matches!(
+			verify_proof::<u32>(&root, &key(3), &storage.prove(&key(3))),
+			Err(ProofError::InvalidValue(_))
+		));
+
+		// Proofs are only valid against the state they were made for.
+		storage.insert(&key(3), &31u64);
+		assert_eq!(
+			verify_proof::<u64>(&root, &key(3), &storage.prove(&key(3))),
+			Err(ProofError::InvalidProof)
+		);
+	}
+}
@@ -0,0 +1,295 @@
+//! The sudo pallet, giving a single account root access to the runtime.
+//!
+//! The account holding the sudo key can dispatch any call with the root origin, or on behalf of any
+//! other account, e.g. to administer a test network through extrinsics.
+
+use crate::{
+	codec::{Decode, Encode},
+	storage::{Storage, StorageValue},
+	support::{
+		Dispatch, DispatchError, DispatchResult, DispatchResultWithPostInfo, GetDispatchInfo,
+		GetPallet, Hooks, PostDispatchInfo, RawOrigin, TransactionalVec, Weight, ensure_signed,
+	},
+	types::SystemConfig,
+};
+use core::fmt::Debug;
+
+pub trait Config:
+	SystemConfig
+	+ Sized
+	+ GetPallet<Pallet<Self>>
+	+ Dispatch<Origin = <Self as SystemConfig>::RuntimeOrigin, Call = <Self as Config>::RuntimeCall>
+{
+	/// The aggregated call type of the runtime, which the sudo key can dispatch.
+	type RuntimeCall: GetDispatchInfo + Clone + Encode + Decode;
+}
+
+/// The sudo pallet.
+pub struct Pallet<T: Config> {
+	/// The storage of the runtime, holding the storage items of this pallet.
+	pub storage: Storage,
+	/// The events deposited during the current extrinsic.
+	pub events: TransactionalVec<Event<T>>,
+}
+
+/// The genesis configuration of the sudo pallet.
+#[derive(serde::Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct GenesisConfig<T: Config> {
+	/// The account holding the sudo key, if any.
+	pub key: Option<T::AccountId>,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+	fn default() -> Self {
+		Self { key: None }
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	pub fn new(storage: Storage) -> Self {
+		Self { storage, events: TransactionalVec::new() }
+	}
+
+	/// Set up the genesis state of the pallet.
+	pub fn build_genesis(&mut self, config: &GenesisConfig<T>) -> DispatchResult {
+		if let Some(key) = &config.key {
+			self.key().insert(key);
+		}
+		Ok(())
+	}
+
+	/// Ensure `origin` is signed by the account holding the sudo key, returning that account.
+	fn ensure_sudo(&self, origin: T::RuntimeOrigin) -> Result<T::AccountId, DispatchError> {
+		let caller = ensure_signed(origin)?;
+		if self.key().get().as_ref() != Some(&caller) {
+			return Err(Error::<T>::RequireSudo.into());
+		}
+		Ok(caller)
+	}
+
+	/// Dispatch `call` on behalf of `origin`, returning its result and the weight it actually used.
+	fn dispatch_as(
+		runtime: &mut T,
+		origin: RawOrigin<T::AccountId>,
+		call: T::RuntimeCall,
+	) -> (DispatchResult, Weight) {
+		let info = call.get_dispatch_info();
+		let (result, post_info) = match runtime.dispatch(origin.into(), call) {
+			Ok(post_info) => (Ok(()), post_info),
+			Err(error) => (Err(error.error), error.post_info),
+		};
+		(result, post_info.calc_actual_weight(&info))
+	}
+}
+
+#[macros::storage]
+impl<T: Config> Pallet<T> {
+	/// The account holding the sudo key.
+	pub type Key = StorageValue<T::AccountId>;
+}
+
+#[macros::error]
+pub enum Error<T: Config> {
+	/// The caller does not hold the sudo key.
+	RequireSudo,
+}
+
+#[macros::event]
+pub enum Event<T: Config> {
+	/// A call was dispatched with the root origin.
+	Sudid { sudo_result: DispatchResult },
+	/// The sudo key was handed from `old` to `new`.
+	KeyChanged { old: T::AccountId, new: T::AccountId },
+	/// A call was dispatched on behalf of another account.
+	SudoAsDone { sudo_result: DispatchResult },
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Dispatch `call` with the root origin. Only callable by the sudo key.
+	///
+	/// This succeeds even if `call` fails, reporting its result in `Event::Sudid`.
+	///
+	/// The call is boxed, since `Call` would otherwise contain itself through `RuntimeCall`.
+	#[weight(call.get_dispatch_info().weight.saturating_add(10_000))]
+	#[allow(clippy::boxed_local)]
+	pub fn sudo(
+		runtime: &mut T,
+		origin: T::RuntimeOrigin,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResultWithPostInfo {
+		runtime.pallet().ensure_sudo(origin)?;
+		let (sudo_result, weight) = Self::dispatch_as(runtime, RawOrigin::Root, *call);
+		runtime.pallet().deposit_event(Event::Sudid { sudo_result });
+		Ok(PostDispatchInfo { actual_weight: Some(weight.saturating_add(10_000)) })
+	}
+
+	/// Dispatch `call` with the signed origin of `who`. Only callable by the sudo key.
+	///
+	/// This succeeds even if `call` fails, reporting its result in `Event::SudoAsDone`.
+	#[weight(call.get_dispatch_info().weight.saturating_add(10_000))]
+	#[allow(clippy::boxed_local)]
+	pub fn sudo_as(
+		runtime: &mut T,
+		origin: T::RuntimeOrigin,
+		who: T::AccountId,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResultWithPostInfo {
+		runtime.pallet().ensure_sudo(origin)?;
+		let (sudo_result, weight) = Self::dispatch_as(runtime, RawOrigin::Signed(who), *call);
+		runtime.pallet().deposit_event(Event::SudoAsDone { sudo_result });
+		Ok(PostDispatchInfo { actual_weight: Some(weight.saturating_add(10_000)) })
+	}
+
+	/// Hand the sudo key over to `new`. Only callable by the sudo key.
+	#[weight(10_000)]
+	pub fn set_key(&mut self, origin: T::RuntimeOrigin, new: T::AccountId) -> DispatchResult {
+		let old = self.ensure_sudo(origin)?;
+		self.key().insert(&new);
+		self.deposit_event(Event::KeyChanged { old, new });
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> Debug for Pallet<T>
+where
+	T::AccountId: Debug,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("Pallet")
+			.field("key", &self.key().get())
+			.field("events", &self.events)
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Event, GenesisConfig};
+	use crate::{
+		balances,
+		codec::{self, Decode, Encode},
+		crypto::Keyring,
+		support::{Dispatch, DispatchError, GetDispatchInfo, MAX_EXTRINSIC_DEPTH, RawOrigin},
+		types::{self, Runtime, RuntimeCall},
+	};
+
+	fn sudo(call: RuntimeCall) -> RuntimeCall {
+		RuntimeCall::Sudo(super::Call::Sudo { call: Box::new(call) })
+	}
+
+	fn force_set_balance(who: Keyring, amount: u128) -> RuntimeCall {
+		let who = who.to_account_id();
+		RuntimeCall::Balances(balances::Call::ForceSetBalance { who, amount })
+	}
+
+	/// A runtime where Alice holds the sudo key.
+	fn new_runtime() -> Runtime {
+		let genesis = types::GenesisConfig {
+			sudo: GenesisConfig { key: Some(Keyring::Alice.to_account_id()) },
+			..Default::default()
+		};
+		Runtime::from_genesis(&genesis).unwrap()
+	}
+
+	#[test]
+	fn sudo_dispatches_with_the_root_origin() {
+		let mut runtime = new_runtime();
+		let alice = RawOrigin::Signed(Keyring::Alice.to_account_id());
+		let bob = RawOrigin::Signed(Keyring::Bob.to_account_id());
+		let call = sudo(force_set_balance(Keyring::Bob, 10_000));
+
+		// Only the sudo key can use sudo.
+		let result = runtime.dispatch(bob, call.clone()).map_err(|error| error.error);
+		assert_eq!(result, Err(super::Error::<Runtime>::RequireSudo.into()));
+		assert_eq!(runtime.balances.balance(&Keyring::Bob.to_account_id()), 0);
+
+		assert!(runtime.dispatch(alice.clone(), call).is_ok());
+		assert_eq!(runtime.balances.balance(&Keyring::Bob.to_account_id()), 10_000);
+		assert_eq!(runtime.sudo.take_events(), vec![Event::Sudid { sudo_result: Ok(()) }]);
+
+		// The sudo call succeeds even if the call it dispatches fails, reporting its result.
+		let transfer = balances::Call::Transfer { to: Keyring::Bob.to_account_id(), amount: 1 };
+		let call = sudo(RuntimeCall::Balances(transfer));
+		assert!(runtime.dispatch(alice, call).is_ok());
+		let sudo_result = Err(DispatchError::BadOrigin);
+		assert_eq!(runtime.sudo.take_events(), vec![Event::Sudid { sudo_result }]);
+	}
+
+	#[test]
+	fn sudo_as_dispatches_on_behalf_of_another_account() {
+		let mut runtime = new_runtime();
+		let alice = RawOrigin::Signed(Keyring::Alice.to_account_id());
+		let bob = Keyring::Bob.to_account_id();
+		runtime.balances.set_balance(&bob, 10_000).unwrap();
+
+		let transfer =
+			balances::Call::Transfer { to: Keyring::Charlie.to_account_id(), amount: 6_000 };
+		let call =
+			super::Call::SudoAs { who: bob, call: Box::new(RuntimeCall::Balances(transfer)) };
+		let call = RuntimeCall::Sudo(call);
+		// The weight of a sudo call includes the weight of the call it dispatches.
+		assert_eq!(call.get_dispatch_info().weight, 20_000);
+
+		assert!(runtime.dispatch(alice.clone(), call.clone()).is_ok());
+		assert_eq!(runtime.balances.balance(&bob), 4_000);
+		assert_eq!(runtime.balances.balance(&Keyring::Charlie.to_account_id()), 6_000);
+		assert_eq!(runtime.sudo.take_events(), vec![Event::SudoAsDone { sudo_result: Ok(()) }]);
+
+		// A failing call leaves no changes behind, but the sudo call itself still succeeds.
+		assert!(runtime.dispatch(alice, call).is_ok());
+		assert_eq!(runtime.balances.balance(&bob), 4_000);
+		let sudo_result = Err(balances::Error::<Runtime>::InsufficientBalance.into());
+		assert_eq!(runtime.sudo.take_events(), vec![Event::SudoAsDone { sudo_result }]);
+	}
+
+	#[test]
+	fn set_key_hands_over_the_sudo_key() {
+		let mut runtime = new_runtime();
+		let alice = Keyring::Alice.to_account_id();
+		let bob = Keyring::Bob.to_account_id();
+
+		let call = RuntimeCall::Sudo(super::Call::SetKey { new: bob });
+		assert!(runtime.dispatch(RawOrigin::Signed(alice), call).is_ok());
+		assert_eq!(runtime.sudo.key().get(), Some(bob));
+		assert_eq!(runtime.sudo.take_events(), vec![Event::KeyChanged { old: alice, new: bob }]);
+
+		// Alice no longer holds the sudo key.
+		let call = sudo(force_set_balance(Keyring::Alice, 10_000));
+		let result = runtime.dispatch(RawOrigin::Signed(alice), call).map_err(|error| error.error);
+		assert_eq!(result, Err(super::Error::<Runtime>::RequireSudo.into()));
+	}
+
+	#[test]
+	fn nested_calls_are_limited_in_depth() {
+		// The encoding of a transfer from Alice nested inside `depth - 1` calls made by the sudo key
+		// on behalf of Alice.
+		let alice = Keyring::Alice.to_account_id();
+		let transfer =
+			balances::Call::Transfer { to: Keyring::Bob.to_account_id(), amount: 10_000 };
+		let leaf = RuntimeCall::Balances(transfer).encode();
+		let nested = |depth: usize| {
+			let call = Box::new(RuntimeCall::decode_all(&leaf).unwrap());
+			let sudo_as = RuntimeCall::Sudo(super::Call::SudoAs { who: alice, call }).encode();
+			let mut encoded = sudo_as[..sudo_as.len() - leaf.len()].repeat(depth - 1);
+			encoded.extend(&leaf);
+			encoded
+		};
+		let too_deep = Some(codec::Error::Invalid("value nested too deeply"));
+		let depth = MAX_EXTRINSIC_DEPTH as usize;
+		assert_eq!(RuntimeCall::decode_all(&nested(depth + 1)).err(), too_deep);
+		// Far deeper calls are rejected just the same, rather than overflowing the stack.
+		assert_eq!(RuntimeCall::decode_all(&nested(200_000)).err(), too_deep);
+
+		// Calls at the limit can be decoded, weighed and dispatched.
+		let call = RuntimeCall::decode_all(&nested(depth)).unwrap();
+		assert_eq!(call.get_dispatch_info().weight, 10_000 * depth as u64);
+		let mut runtime = new_runtime();
+		runtime.balances.set_balance(&alice, 100_000).unwrap();
+		assert!(runtime.dispatch(RawOrigin::Signed(alice), call).is_ok());
+		assert_eq!(runtime.balances.balance(&Keyring::Bob.to_account_id()), 10_000);
+	}
+}
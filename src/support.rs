@@ -1,4 +1,15 @@
+use crate::{
+	codec::Encode,
+	crypto::{AccountId32, H256, MultiSignature, Pair, Verify, blake2_256, merkle_root},
+};
+use core::{
+	borrow::Borrow,
+	fmt::{Debug, Display},
+};
+use std::collections::BTreeMap;
+
 /// The most primitive representation of a Blockchain block.
+#[derive(Clone, macros::Encode, macros::Decode)]
 pub struct Block<Header, Extrinsic> {
 	/// The block header contains metadata about the block.
 	pub header: Header,
@@ -6,37 +17,767 @@ pub struct Block<Header, Extrinsic> {
 	pub extrinsics: Vec<Extrinsic>,
 }
 
-/// We are using an extremely simplified header which only contains the current block number.
-/// On a real blockchain, you would expect to also find:
-/// - parent block hash
-/// - state root
-/// - extrinsics root
-/// - etc...
+/// The header of a block, which commits to everything the block contains and what it results in.
+///
+/// The hash of the header identifies the block, and chains it to its parent.
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
 pub struct Header<BlockNumber> {
+	/// The hash of the header of the previous block.
+	pub parent_hash: H256,
 	pub block_number: BlockNumber,
+	/// The root of the state after executing the block.
+	pub state_root: H256,
+	/// The merkle root of the extrinsics of the block, see `extrinsics_root`.
+	pub extrinsics_root: H256,
+	pub digest: Digest,
+}
+
+impl<BlockNumber: Encode> Header<BlockNumber> {
+	/// The hash of the header, identifying the block.
+	pub fn hash(&self) -> H256 {
+		blake2_256(&self.encode())
+	}
+}
+
+/// Extra information about a block which is not checked by the runtime, like consensus data.
+#[derive(Debug, Clone, Default, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct Digest {
+	pub logs: Vec<DigestItem>,
+}
+
+/// A single item of a `Digest`.
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub enum DigestItem {
+	/// Data provided to the runtime by a consensus engine, identified by its id.
+	PreRuntime([u8; 4], Vec<u8>),
+	/// Any other data.
+	Other(Vec<u8>),
+}
+
+/// The merkle root of the hashes of the encoded `extrinsics`, in order.
+pub fn extrinsics_root<E: Encode>(extrinsics: &[E]) -> H256 {
+	merkle_root(extrinsics.iter().map(|extrinsic| blake2_256(&extrinsic.encode())))
 }
 
 /// This is an "extrinsic": literally an external message from outside of the blockchain.
-/// This simplified version of an extrinsic tells us who is making the call, and which call they are
-/// making.
-pub struct Extrinsic<Caller, Call> {
+/// This simplified version of an extrinsic tells us who is making the call, which call they are
+/// making, and the nonce of the caller it is valid for.
+pub struct Extrinsic<Caller, Nonce, Call> {
 	pub caller: Caller,
+	/// Must be the current nonce of the caller, so the same extrinsic can not be executed twice.
+	pub nonce: Nonce,
 	pub call: Call,
 }
 
+/// An extrinsic as it is submitted to the chain, signed by its caller.
+///
+/// The signature covers the encoded call, the nonce, and the number and hash of the block it was
+/// signed at, its `birth`. So it can not be replayed on another chain, or with another nonce.
+/// Extrinsics are mortal: they can only be included in the `SystemConfig::MORTALITY_PERIOD` blocks
+/// following their birth. So once the account of their signer is reaped, resetting its nonce, they
+/// can only be replayed if the account is funded again before they expire.
+#[derive(Clone, macros::Encode, macros::Decode)]
+pub struct UncheckedExtrinsic<Caller, Nonce, BlockNumber, Call, Signature> {
+	pub signer: Caller,
+	pub nonce: Nonce,
+	pub birth: BlockNumber,
+	pub call: Call,
+	pub signature: Signature,
+}
+
+impl<Caller, Nonce: Encode, BlockNumber: Encode, Call: Encode, Signature>
+	UncheckedExtrinsic<Caller, Nonce, BlockNumber, Call, Signature>
+{
+	/// The payload signed by the signer of an extrinsic born at the block `birth`, whose hash is
+	/// `birth_hash`.
+	pub fn signing_payload(
+		call: &Call,
+		nonce: &Nonce,
+		birth: &BlockNumber,
+		birth_hash: &H256,
+	) -> Vec<u8> {
+		(call, nonce, birth, birth_hash).encode()
+	}
+}
+
+impl<Nonce: Encode, BlockNumber: Encode, Call: Encode>
+	UncheckedExtrinsic<AccountId32, Nonce, BlockNumber, Call, MultiSignature>
+{
+	/// Create an extrinsic signed by `pair` at the block `birth`, whose hash is `birth_hash`.
+	pub fn new_signed(
+		pair: &Pair,
+		nonce: Nonce,
+		call: Call,
+		birth: BlockNumber,
+		birth_hash: &H256,
+	) -> Self {
+		let signature = pair.sign(&Self::signing_payload(&call, &nonce, &birth, birth_hash));
+		Self { signer: pair.account_id(), nonce, birth, call, signature }
+	}
+}
+
+/// Something which must be checked before it can be used, turning it into `Self::Checked`.
+pub trait Checkable<Context> {
+	/// The checked version of this type.
+	type Checked;
+
+	/// Check `self`, given the `context` it must be valid in.
+	fn check(self, context: &Context) -> Result<Self::Checked, InvalidTransaction>;
+}
+
+/// Extrinsics are checked against the hash of the block they were signed at, turning them into an
+/// `Extrinsic` once their signature is verified.
+impl<Caller, Nonce, BlockNumber, Call, Signature> Checkable<H256>
+	for UncheckedExtrinsic<Caller, Nonce, BlockNumber, Call, Signature>
+where
+	Nonce: Encode,
+	BlockNumber: Encode,
+	Call: Encode,
+	Signature: Verify<Signer = Caller>,
+{
+	type Checked = Extrinsic<Caller, Nonce, Call>;
+
+	fn check(self, birth_hash: &H256) -> Result<Self::Checked, InvalidTransaction> {
+		let payload = Self::signing_payload(&self.call, &self.nonce, &self.birth, birth_hash);
+		if !self.signature.verify(&payload, &self.signer) {
+			return Err(InvalidTransaction::BadProof);
+		}
+		Ok(Extrinsic { caller: self.signer, nonce: self.nonce, call: self.call })
+	}
+}
+
+/// The result of executing a block. An `Err` means the whole block was rejected.
+pub type BlockResult = Result<(), BlockError>;
+
+/// The reasons a block can be rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum BlockError {
+	/// The block number does not follow the number of the previous block.
+	InvalidBlockNumber,
+	/// The parent hash of the block is not the hash of the previous block.
+	InvalidParentHash,
+	/// The extrinsics root of the block does not match its extrinsics.
+	InvalidExtrinsicsRoot,
+	/// The state root of the block does not match the state after executing it.
+	InvalidStateRoot,
+	/// The extrinsic at `extrinsic_index` can not be included in the block.
+	InvalidTransaction { extrinsic_index: u32, error: InvalidTransaction },
+}
+
+/// The reasons an extrinsic can not be included in a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidTransaction {
+	/// The nonce of the extrinsic was already used by the caller.
+	Stale,
+	/// The nonce of the extrinsic is ahead of the caller's next nonce.
+	Future,
+	/// The signature of the extrinsic is not valid.
+	BadProof,
+	/// The caller can not pay the fee of the extrinsic.
+	Payment,
+	/// The extrinsic would take the block over its weight limit.
+	ExhaustsResources,
+	/// The extrinsic was signed at a block which is unknown, or more than
+	/// `SystemConfig::MORTALITY_PERIOD` blocks ago.
+	AncientBirthBlock,
+}
+
 /// The Result type for our runtime. When everything completes successfully, we return `Ok(())`,
-/// otherwise return a static error message.
-pub type DispatchResult = Result<(), &'static str>;
+/// otherwise return a `DispatchError` describing what went wrong.
+pub type DispatchResult = Result<(), DispatchError>;
+
+/// The result of dispatching a call, along with what is only known once it has been dispatched,
+/// like the weight it actually used.
+pub type DispatchResultWithPostInfo = Result<PostDispatchInfo, DispatchErrorWithPostInfo>;
+
+/// The computational cost of a call, which limits how much can fit in a block and sets its fee.
+pub type Weight = u64;
+
+/// The most calls which can be nested inside each other, e.g. through sudo or batches, counting the
+/// outermost call. Decoding, weighing and dispatching a call recurse once per level, so deeper
+/// calls are rejected when they are decoded.
+pub const MAX_EXTRINSIC_DEPTH: u32 = 256;
+
+/// What is known about a call before dispatching it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DispatchInfo {
+	/// The weight declared by the call, which is the most it may use.
+	pub weight: Weight,
+}
+
+/// What is known about a call once it has been dispatched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PostDispatchInfo {
+	/// The weight actually used by the call, or `None` if it used all of its declared weight.
+	pub actual_weight: Option<Weight>,
+}
+
+impl PostDispatchInfo {
+	/// The weight actually used by a call with the given `info`, which is never more than its
+	/// declared weight.
+	pub fn calc_actual_weight(&self, info: &DispatchInfo) -> Weight {
+		self.actual_weight.map_or(info.weight, |weight| weight.min(info.weight))
+	}
+}
+
+/// Calls returning `DispatchResult` use all of their declared weight.
+impl From<()> for PostDispatchInfo {
+	fn from(_: ()) -> Self {
+		Self::default()
+	}
+}
+
+/// The reason a dispatch failed, along with the weight it used before failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DispatchErrorWithPostInfo {
+	pub post_info: PostDispatchInfo,
+	pub error: DispatchError,
+}
+
+impl<E: Into<DispatchError>> From<E> for DispatchErrorWithPostInfo {
+	fn from(error: E) -> Self {
+		Self { post_info: PostDispatchInfo::default(), error: error.into() }
+	}
+}
+
+/// Attach the weight a call actually used to an error, for calls which fail before using all of
+/// their declared weight.
+pub trait WithPostDispatchInfo {
+	fn with_weight(self, actual_weight: Weight) -> DispatchErrorWithPostInfo;
+}
+
+impl<E: Into<DispatchError>> WithPostDispatchInfo for E {
+	fn with_weight(self, actual_weight: Weight) -> DispatchErrorWithPostInfo {
+		let post_info = PostDispatchInfo { actual_weight: Some(actual_weight) };
+		DispatchErrorWithPostInfo { post_info, error: self.into() }
+	}
+}
+
+/// The reason a dispatch failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DispatchError {
+	/// An error declared by a pallet, identified by the pallet's index in the runtime and the
+	/// index of the variant in the pallet's `Error<T>` enum.
+	Module { pallet_index: u8, error_index: u8 },
+	/// The origin of the call is not allowed to make it.
+	BadOrigin,
+	/// An arithmetic operation failed.
+	Arithmetic(ArithmeticError),
+	/// Any other error, described by a static message.
+	Other(&'static str),
+}
+
+/// The arithmetic errors a dispatch can fail with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithmeticError {
+	/// The result of the operation is smaller than the minimum value of its type.
+	#[allow(dead_code)]
+	Underflow,
+	/// The result of the operation is larger than the maximum value of its type.
+	Overflow,
+}
+
+impl From<&'static str> for DispatchError {
+	fn from(message: &'static str) -> Self {
+		Self::Other(message)
+	}
+}
+
+impl From<ArithmeticError> for DispatchError {
+	fn from(error: ArithmeticError) -> Self {
+		Self::Arithmetic(error)
+	}
+}
+
+impl Display for DispatchError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::Module { pallet_index, error_index } => {
+				write!(f, "Module error (pallet: {pallet_index}, error: {error_index})")
+			},
+			Self::BadOrigin => write!(f, "Bad origin"),
+			Self::Arithmetic(error) => write!(f, "Arithmetic error: {error:?}"),
+			Self::Other(message) => write!(f, "{message}"),
+		}
+	}
+}
+
+/// Provides information about the pallets included in a runtime.
+pub trait PalletInfo {
+	/// The index of the pallet `P` in the runtime, or `None` if it is not part of the runtime.
+	fn index<P: 'static>() -> Option<usize>;
+	/// The name of the pallet `P` in the runtime, or `None` if it is not part of the runtime. This
+	/// is the prefix of the keys of its storage items.
+	fn name<P: 'static>() -> Option<&'static str>;
+}
+
+/// Pallet information for a runtime with a single pallet, which is handy in pallet unit tests.
+/// Pallets are named after their type, so they never share storage even if there are several.
+impl PalletInfo for () {
+	fn index<P: 'static>() -> Option<usize> {
+		Some(0)
+	}
+
+	fn name<P: 'static>() -> Option<&'static str> {
+		Some(core::any::type_name::<P>())
+	}
+}
+
+/// The origin of a call, i.e. on whose behalf it is made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawOrigin<AccountId> {
+	/// The call is made with the highest privileges, e.g. by governance.
+	Root,
+	/// The call is made by an account, which signed the extrinsic.
+	Signed(AccountId),
+}
+
+/// Ensure `origin` is a signed origin, returning the account which signed.
+pub fn ensure_signed<AccountId>(
+	origin: impl Into<RawOrigin<AccountId>>,
+) -> Result<AccountId, DispatchError> {
+	match origin.into() {
+		RawOrigin::Signed(who) => Ok(who),
+		_ => Err(DispatchError::BadOrigin),
+	}
+}
+
+/// Ensure `origin` is the root origin.
+pub fn ensure_root<AccountId>(
+	origin: impl Into<RawOrigin<AccountId>>,
+) -> Result<(), DispatchError> {
+	match origin.into() {
+		RawOrigin::Root => Ok(()),
+		_ => Err(DispatchError::BadOrigin),
+	}
+}
 
 /// A trait which allows us to dispatch an incoming extrinsic to the appropriate state transition
 /// function call.
 pub trait Dispatch {
-	/// The type used to identify the caller of the function.
-	type Caller;
-	/// The state transition function call the caller is trying to access.
+	/// The type used to identify the origin of the function call.
+	type Origin;
+	/// The state transition function call the origin is trying to access.
 	type Call;
 
-	/// This function takes a `caller` and the `call` they want to make, and returns a `Result`
-	/// based on the outcome of that function call.
-	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
+	/// This function takes an `origin` and the `call` it wants to make, and returns a `Result`
+	/// based on the outcome of that function call, along with the weight it actually used.
+	fn dispatch(&mut self, origin: Self::Origin, call: Self::Call) -> DispatchResultWithPostInfo;
+}
+
+/// Dispatch the calls of a pallet from within the runtime `R`, which is how the runtime routes its
+/// calls to each pallet.
+///
+/// Most pallets only need their own state to dispatch a call, but some calls need the rest of the
+/// runtime too, e.g. to dispatch another call on behalf of a different origin.
+pub trait DispatchIn<R> {
+	/// The type used to identify the origin of the function call.
+	type Origin;
+	/// The state transition function call the origin is trying to access.
+	type Call;
+
+	/// Dispatch `call` on behalf of `origin`, with access to the whole `runtime`.
+	fn dispatch_in(
+		runtime: &mut R,
+		origin: Self::Origin,
+		call: Self::Call,
+	) -> DispatchResultWithPostInfo;
+}
+
+/// Gives access to the pallet `P` of a runtime.
+pub trait GetPallet<P> {
+	fn pallet(&mut self) -> &mut P;
+}
+
+/// A call which declares its `DispatchInfo`, such as its weight, before being dispatched.
+pub trait GetDispatchInfo {
+	fn get_dispatch_info(&self) -> DispatchInfo;
+}
+
+/// Logic a pallet runs within the runtime `R` at the boundaries of every block.
+///
+/// The runtime calls the hooks of every pallet in declaration order: `on_initialize` before the
+/// extrinsics of the block are dispatched, then `on_idle` and `on_finalize` once they have been.
+/// Every hook does nothing by default.
+pub trait Hooks<R, BlockNumber> {
+	/// Initialize the block `n`, returning the weight used, including the weight `on_finalize` will
+	/// use. It counts towards the weight of the block, leaving less room for extrinsics.
+	fn on_initialize(_runtime: &mut R, _n: BlockNumber) -> Weight {
+		0
+	}
+
+	/// Use up to `remaining_weight`, the weight left unused in the block `n`, for work which can
+	/// wait for a block with room to spare. Returns the weight used.
+	fn on_idle(_runtime: &mut R, _n: BlockNumber, _remaining_weight: Weight) -> Weight {
+		0
+	}
+
+	/// Finalize the block `n`, e.g. to clean up state which only lasts until a given block.
+	fn on_finalize(_runtime: &mut R, _n: BlockNumber) {}
+}
+
+/// Charges callers for their extrinsics, before they are dispatched.
+pub trait ChargeTransaction {
+	/// The type used to identify the caller of an extrinsic.
+	type Caller;
+
+	/// Withdraw the fee of an extrinsic of `len` bytes, with the given `info`, from `who`.
+	fn withdraw_fee(
+		&mut self,
+		who: &Self::Caller,
+		info: &DispatchInfo,
+		len: u32,
+	) -> Result<(), InvalidTransaction>;
+
+	/// Refund `who` the part of the fee paid for the weight an extrinsic did not use.
+	fn refund_fee(&mut self, who: &Self::Caller, info: &DispatchInfo, post_info: &PostDispatchInfo);
+}
+
+/// Whether taking funds out of an account may reap it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExistenceRequirement {
+	/// The account must keep at least the existential deposit.
+	KeepAlive,
+	/// The account may drop below the existential deposit, in which case it is reaped.
+	AllowDeath,
+}
+
+/// A fungible currency, held in the free balance of accounts.
+pub trait Currency<AccountId> {
+	/// The type of an amount of the currency.
+	type Balance;
+
+	/// Move `value` from the free balance of `from` to `to`, reaping `from` if this leaves it below
+	/// the existential deposit and `existence` allows it.
+	fn transfer(
+		&mut self,
+		from: &AccountId,
+		to: &AccountId,
+		value: Self::Balance,
+		existence: ExistenceRequirement,
+	) -> DispatchResult;
+
+	/// Destroy `value` of the free balance of `who`, e.g. to pay a fee.
+	fn withdraw(
+		&mut self,
+		who: &AccountId,
+		value: Self::Balance,
+		existence: ExistenceRequirement,
+	) -> DispatchResult;
+
+	/// Create `value` into the free balance of `who`, creating the account if it does not exist.
+	fn deposit_creating(&mut self, who: &AccountId, value: Self::Balance) -> DispatchResult;
+}
+
+/// A currency where funds can be reserved, e.g. as a deposit. Reserved funds still belong to their
+/// account, but can not be spent until they are unreserved.
+pub trait ReservableCurrency<AccountId>: Currency<AccountId> {
+	/// Whether `value` of the free balance of `who` can be reserved.
+	fn can_reserve(&self, who: &AccountId, value: Self::Balance) -> bool;
+
+	/// The reserved balance of `who`.
+	fn reserved_balance(&self, who: &AccountId) -> Self::Balance;
+
+	/// Move `value` from the free balance of `who` to its reserved balance.
+	fn reserve(&mut self, who: &AccountId, value: Self::Balance) -> DispatchResult;
+
+	/// Move up to `value` from the reserved balance of `who` back to its free balance. Returns the
+	/// part of `value` which could not be unreserved.
+	fn unreserve(&mut self, who: &AccountId, value: Self::Balance) -> Self::Balance;
+
+	/// Move up to `value` from the reserved balance of `slashed` to the reserved balance of
+	/// `beneficiary`. Returns the part of `value` which could not be moved.
+	fn repatriate_reserved(
+		&mut self,
+		slashed: &AccountId,
+		beneficiary: &AccountId,
+		value: Self::Balance,
+	) -> Result<Self::Balance, DispatchError>;
+}
+
+/// A trait for state which can be modified inside of a storage transaction.
+///
+/// Transactions can be nested: every `start_transaction` opens a new layer, which must later be
+/// closed with either `commit_transaction` (merging its changes into the layer below) or
+/// `rollback_transaction` (throwing its changes away).
+pub trait Transactional {
+	/// Open a new transactional layer on top of the current state.
+	fn start_transaction(&mut self);
+	/// Merge the changes of the top-most transactional layer into the layer below it.
+	fn commit_transaction(&mut self);
+	/// Discard all the changes made in the top-most transactional layer.
+	fn rollback_transaction(&mut self);
+
+	/// Execute `f` inside a new transactional layer. The layer is committed if `f` returns `Ok`,
+	/// and rolled back if it returns `Err`.
+	fn with_transaction<R, E>(&mut self, f: impl FnOnce(&mut Self) -> Result<R, E>) -> Result<R, E>
+	where
+		Self: Sized,
+	{
+		self.start_transaction();
+		let result = f(self);
+		match result {
+			Ok(_) => self.commit_transaction(),
+			Err(_) => self.rollback_transaction(),
+		}
+		result
+	}
+}
+
+/// A key-value map which supports nested storage transactions.
+///
+/// Writes made while a transaction is open are buffered in an overlay, and only reach the
+/// committed state once every transaction above them has been committed.
+pub struct TransactionalMap<K, V> {
+	/// The committed state of the map.
+	committed: BTreeMap<K, V>,
+	/// The stack of open transactional layers. `None` marks a key removed in that layer.
+	overlays: Vec<BTreeMap<K, Option<V>>>,
+}
+
+impl<K: Ord, V> TransactionalMap<K, V> {
+	pub fn new() -> Self {
+		Self { committed: BTreeMap::new(), overlays: Vec::new() }
+	}
+
+	pub fn get<Q>(&self, key: &Q) -> Option<&V>
+	where
+		K: Borrow<Q>,
+		Q: Ord + ?Sized,
+	{
+		for overlay in self.overlays.iter().rev() {
+			if let Some(value) = overlay.get(key) {
+				return value.as_ref();
+			}
+		}
+		self.committed.get(key)
+	}
+
+	pub fn contains_key<Q>(&self, key: &Q) -> bool
+	where
+		K: Borrow<Q>,
+		Q: Ord + ?Sized,
+	{
+		self.get(key).is_some()
+	}
+
+	pub fn insert(&mut self, key: K, value: V) {
+		match self.overlays.last_mut() {
+			Some(overlay) => {
+				overlay.insert(key, Some(value));
+			},
+			None => {
+				self.committed.insert(key, value);
+			},
+		}
+	}
+
+	pub fn remove(&mut self, key: K) {
+		match self.overlays.last_mut() {
+			Some(overlay) => {
+				overlay.insert(key, None);
+			},
+			None => {
+				self.committed.remove(&key);
+			},
+		}
+	}
+
+	/// Iterate over the current view of the map, including any uncommitted changes.
+	pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+		let mut view: BTreeMap<&K, &V> = self.committed.iter().collect();
+		for overlay in &self.overlays {
+			for (key, value) in overlay {
+				match value {
+					Some(value) => view.insert(key, value),
+					None => view.remove(key),
+				};
+			}
+		}
+		view.into_iter()
+	}
+}
+
+impl<K: Ord, V> Default for TransactionalMap<K, V> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<K: Ord, V> Transactional for TransactionalMap<K, V> {
+	fn start_transaction(&mut self) {
+		self.overlays.push(BTreeMap::new());
+	}
+
+	fn commit_transaction(&mut self) {
+		let overlay = self.overlays.pop().expect("no open transaction to commit");
+		for (key, value) in overlay {
+			match self.overlays.last_mut() {
+				Some(parent) => {
+					parent.insert(key, value);
+				},
+				None => match value {
+					Some(value) => {
+						self.committed.insert(key, value);
+					},
+					None => {
+						self.committed.remove(&key);
+					},
+				},
+			}
+		}
+	}
+
+	fn rollback_transaction(&mut self) {
+		self.overlays.pop().expect("no open transaction to roll back");
+	}
+}
+
+impl<K: Ord + Debug, V: Debug> Debug for TransactionalMap<K, V> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
+/// A list which supports nested storage transactions.
+///
+/// Items pushed while a transaction is open are dropped if that transaction is rolled back.
+pub struct TransactionalVec<T> {
+	/// All the items, including the ones pushed in open transactions.
+	items: Vec<T>,
+	/// The length of `items` when each of the open transactions was started.
+	checkpoints: Vec<usize>,
+}
+
+impl<T> TransactionalVec<T> {
+	pub fn new() -> Self {
+		Self { items: Vec::new(), checkpoints: Vec::new() }
+	}
+
+	pub fn push(&mut self, item: T) {
+		self.items.push(item);
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &T> {
+		self.items.iter()
+	}
+
+	/// Take all the items out of the list.
+	///
+	/// Items taken while a transaction is open are not restored if it is rolled back.
+	pub fn take(&mut self) -> Vec<T> {
+		for checkpoint in &mut self.checkpoints {
+			*checkpoint = 0;
+		}
+		core::mem::take(&mut self.items)
+	}
+}
+
+impl<T> Default for TransactionalVec<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T> Transactional for TransactionalVec<T> {
+	fn start_transaction(&mut self) {
+		self.checkpoints.push(self.items.len());
+	}
+
+	fn commit_transaction(&mut self) {
+		self.checkpoints.pop().expect("no open transaction to commit");
+	}
+
+	fn rollback_transaction(&mut self) {
+		let len = self.checkpoints.pop().expect("no open transaction to roll back");
+		self.items.truncate(len);
+	}
+}
+
+impl<T: Debug> Debug for TransactionalVec<T> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_list().entries(self.iter()).finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		DispatchError, RawOrigin, Transactional, TransactionalMap, TransactionalVec, ensure_root,
+		ensure_signed,
+	};
+
+	#[test]
+	fn origins() {
+		let signed = RawOrigin::Signed("alice");
+		assert_eq!(ensure_signed(signed.clone()), Ok("alice"));
+		assert_eq!(ensure_signed(RawOrigin::<&str>::Root), Err(DispatchError::BadOrigin));
+		assert_eq!(ensure_root(RawOrigin::<&str>::Root), Ok(()));
+		assert_eq!(ensure_root(signed), Err(DispatchError::BadOrigin));
+	}
+
+	#[test]
+	fn nested_transactions() {
+		let mut map = TransactionalMap::<u32, u32>::new();
+		map.insert(1, 10);
+
+		map.start_transaction();
+		map.insert(2, 20);
+		map.start_transaction();
+		map.insert(1, 11);
+		map.remove(2);
+		assert_eq!(map.get(&1), Some(&11));
+		assert_eq!(map.get(&2), None);
+		map.rollback_transaction();
+
+		assert_eq!(map.get(&1), Some(&10));
+		assert_eq!(map.get(&2), Some(&20));
+		map.commit_transaction();
+
+		assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&1, &10), (&2, &20)]);
+	}
+
+	#[test]
+	fn with_transaction_rolls_back_on_error() {
+		let mut map = TransactionalMap::<u32, u32>::new();
+		let result: Result<(), &str> = map.with_transaction(|map| {
+			map.insert(1, 10);
+			Err("failed")
+		});
+
+		assert_eq!(result, Err("failed"));
+		assert_eq!(map.get(&1), None);
+		let result: Result<(), &str> = map.with_transaction(|map| {
+			map.insert(1, 10);
+			Ok(())
+		});
+		assert_eq!(result, Ok(()));
+		assert_eq!(map.get(&1), Some(&10));
+	}
+
+	#[test]
+	fn vec_drops_items_of_rolled_back_transactions() {
+		let mut vec = TransactionalVec::<u32>::new();
+		vec.push(1);
+
+		vec.start_transaction();
+		vec.push(2);
+		vec.start_transaction();
+		vec.push(3);
+		vec.rollback_transaction();
+		vec.commit_transaction();
+
+		assert_eq!(vec.take(), vec![1, 2]);
+		assert_eq!(vec.take(), Vec::<u32>::new());
+
+		// Items taken inside a transaction stay taken, later items are still dropped.
+		vec.push(1);
+		vec.start_transaction();
+		vec.push(2);
+		assert_eq!(vec.take(), vec![1, 2]);
+		vec.push(3);
+		vec.rollback_transaction();
+		assert_eq!(vec.take(), Vec::<u32>::new());
+	}
 }
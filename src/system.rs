@@ -1,47 +1,301 @@
-use crate::types::{SystemConfig, SystemPallet};
+use crate::{
+	crypto::H256,
+	storage::{Storage, StorageMap, StorageValue},
+	support::{DispatchError, DispatchResult, InvalidTransaction, Transactional},
+	types::{EventRecord, Phase, SystemConfig, SystemPallet},
+};
+use core::{cmp::Ordering, fmt::Debug};
 use num::traits::{CheckedAdd, One, Zero};
 use std::collections::BTreeMap;
 
+#[macros::storage]
 impl<T: SystemConfig> SystemPallet<T> {
-	pub fn new() -> Self {
-		Self { block_number: T::BlockNumber::zero(), nonce: BTreeMap::new() }
+	/// The number of the latest block.
+	type Number = StorageValue<T::BlockNumber>;
+	/// The nonce of each account which has made an extrinsic.
+	type AccountNonce = StorageMap<T::AccountId, T::Nonce>;
+}
+
+/// The events deposited by the runtime for every extrinsic, after the events of the pallets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+	/// The extrinsic was dispatched successfully.
+	ExtrinsicSuccess,
+	/// The extrinsic failed to dispatch with `error`. It still paid its fee and used its nonce.
+	ExtrinsicFailed { error: DispatchError },
+}
+
+/// The genesis configuration of the system pallet.
+#[derive(serde::Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct GenesisConfig<T: SystemConfig> {
+	/// The number of the genesis block, which the chain starts from.
+	pub block_number: T::BlockNumber,
+}
+
+impl<T: SystemConfig> Default for GenesisConfig<T> {
+	fn default() -> Self {
+		Self { block_number: T::BlockNumber::zero() }
+	}
+}
+
+impl<T: SystemConfig> SystemPallet<T> {
+	pub fn new(storage: Storage) -> Self {
+		Self { storage, block_hash: BTreeMap::new(), events: Vec::new() }
+	}
+
+	/// Set up the genesis state of the pallet. The hash of the genesis block must be recorded with
+	/// `set_block_hash` once the genesis state of every pallet is set up, since the genesis block
+	/// commits to it.
+	pub fn build_genesis(&mut self, config: &GenesisConfig<T>) -> DispatchResult {
+		self.number().insert(&config.block_number);
+		Ok(())
 	}
 
 	pub fn block_number(&self) -> T::BlockNumber {
-		self.block_number
+		self.number().get().unwrap_or(T::BlockNumber::zero())
 	}
 
-	pub fn inc_block_number(&mut self) {
-		self.block_number = self
-			.block_number
+	/// The number of the block following the latest one.
+	pub fn next_block_number(&self) -> T::BlockNumber {
+		self.block_number()
 			.checked_add(&T::BlockNumber::one())
-			.unwrap_or(T::BlockNumber::one());
+			.unwrap_or(T::BlockNumber::one())
+	}
+
+	pub fn inc_block_number(&mut self) {
+		self.number().insert(&self.next_block_number());
+	}
+
+	/// The hash of the block with the given number, if it has been executed.
+	pub fn block_hash(&self, block_number: T::BlockNumber) -> Option<H256> {
+		self.block_hash.get(&block_number).copied()
+	}
+
+	/// Record the hash of the latest block, once it has been executed.
+	pub fn set_block_hash(&mut self, hash: H256) {
+		self.block_hash.insert(self.block_number(), hash);
+	}
+
+	/// Record the hash of an earlier block, e.g. when restoring the chain from a database.
+	pub fn insert_block_hash(&mut self, block_number: T::BlockNumber, hash: H256) {
+		self.block_hash.insert(block_number, hash);
+	}
+
+	/// The hash of the latest block, which is the parent of the next one.
+	pub fn parent_hash(&self) -> H256 {
+		self.block_hash(self.block_number())
+			.expect("the latest block hash is always recorded")
+	}
+
+	/// The hash of the genesis block, which identifies the chain.
+	pub fn genesis_hash(&self) -> H256 {
+		*self.block_hash.values().next().expect("the genesis hash is always recorded")
+	}
+
+	pub fn nonce(&self, who: &T::AccountId) -> T::Nonce {
+		self.account_nonce().get(who).unwrap_or(T::Nonce::zero())
 	}
 
 	pub fn inc_nonce(&mut self, who: &T::AccountId) {
-		let nonce: T::Nonce = *self.nonce.get(who).unwrap_or(&T::Nonce::zero());
-		let new_nonce = nonce + T::Nonce::one();
-		self.nonce.insert(who.clone(), new_nonce);
+		self.account_nonce().mutate(who, |nonce| {
+			*nonce = Some(nonce.unwrap_or(T::Nonce::zero()) + T::Nonce::one());
+		});
+	}
+
+	/// Remove what the system pallet keeps about `who`, i.e. its nonce, once the account has been
+	/// reaped. Extrinsics are mortal, so those signed by `who` before can not be replayed once they
+	/// are more than `MORTALITY_PERIOD` blocks old.
+	pub fn kill_account(&mut self, who: &T::AccountId) {
+		self.account_nonce().remove(who);
+	}
+
+	/// The hash of the block `birth`, if an extrinsic signed at that block can be included in the
+	/// next block, i.e. if it was signed at most `MORTALITY_PERIOD` blocks before.
+	pub fn validate_mortality(&self, birth: T::BlockNumber) -> Result<H256, InvalidTransaction> {
+		let expires_at = birth.checked_add(&T::MORTALITY_PERIOD);
+		if expires_at.is_some_and(|expires_at| expires_at < self.next_block_number()) {
+			return Err(InvalidTransaction::AncientBirthBlock);
+		}
+		self.block_hash(birth).ok_or(InvalidTransaction::AncientBirthBlock)
+	}
+
+	/// Check that every `(who, nonce)` pair, in order, carries the next nonce of `who`, as if the
+	/// nonces of all the previous pairs had already been used.
+	///
+	/// On failure, returns the position of the first invalid pair and the reason it is invalid.
+	pub fn validate_nonces<'a>(
+		&self,
+		nonces: impl IntoIterator<Item = (&'a T::AccountId, T::Nonce)>,
+	) -> Result<(), (u32, InvalidTransaction)> {
+		let mut next_nonces = BTreeMap::new();
+		for (i, (who, nonce)) in nonces.into_iter().enumerate() {
+			let next_nonce = next_nonces.entry(who).or_insert_with(|| self.nonce(who));
+			match nonce.cmp(next_nonce) {
+				Ordering::Less => return Err((i as u32, InvalidTransaction::Stale)),
+				Ordering::Greater => return Err((i as u32, InvalidTransaction::Future)),
+				Ordering::Equal => *next_nonce = nonce + T::Nonce::one(),
+			}
+		}
+		Ok(())
+	}
+
+	/// The events emitted in the current block, in the order they were deposited.
+	pub fn events(&self) -> &[EventRecord<T::RuntimeEvent>] {
+		&self.events
+	}
+
+	pub fn deposit_event(&mut self, phase: Phase, event: T::RuntimeEvent) {
+		self.events.push(EventRecord { phase, event });
+	}
+
+	/// Clear the events of the previous block. Called at the start of every block.
+	pub fn reset_events(&mut self) {
+		self.events.clear();
+	}
+}
+
+/// Events are only kept for the latest block, so they do not take part in storage transactions.
+impl<T: SystemConfig> Transactional for SystemPallet<T> {
+	fn start_transaction(&mut self) {
+		self.storage.start_transaction();
+	}
+
+	fn commit_transaction(&mut self) {
+		self.storage.commit_transaction();
+	}
+
+	fn rollback_transaction(&mut self) {
+		self.storage.rollback_transaction();
+	}
+}
+
+impl<T: SystemConfig> Debug for SystemPallet<T>
+where
+	T::AccountId: Debug,
+	T::BlockNumber: Debug,
+	T::Nonce: Debug,
+	T::RuntimeEvent: Debug,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("SystemPallet")
+			.field("block_number", &self.block_number())
+			.field("nonce", &BTreeMap::from_iter(self.account_nonce().iter()))
+			.field("events", &self.events)
+			.finish()
 	}
 }
 
 #[cfg(test)]
 mod test {
+	use crate::{
+		storage::Storage,
+		types::{Phase, SystemConfig, SystemPallet},
+	};
+
 	struct TestConfig;
 	impl super::SystemConfig for TestConfig {
+		type PalletInfo = ();
+		type RuntimeEvent = &'static str;
+		type RuntimeOrigin = crate::support::RawOrigin<String>;
 		type AccountId = String;
 		type BlockNumber = u32;
 		type Nonce = u32;
+		const MAXIMUM_BLOCK_WEIGHT: u64 = u64::MAX;
+		const MORTALITY_PERIOD: u32 = 256;
+	}
+
+	impl<T: SystemConfig> SystemPallet<T> {
+		/// The events emitted by the extrinsic at `extrinsic_index` in the current block.
+		pub fn extrinsic_events(
+			&self,
+			extrinsic_index: u32,
+		) -> impl Iterator<Item = &T::RuntimeEvent> {
+			self.events
+				.iter()
+				.filter(move |record| record.phase == Phase::ApplyExtrinsic(extrinsic_index))
+				.map(|record| &record.event)
+		}
 	}
 
 	#[test]
 	fn init_system() {
-		let mut system = super::SystemPallet::<TestConfig>::new();
+		let mut system = super::SystemPallet::<TestConfig>::new(Storage::new());
+		system.build_genesis(&super::GenesisConfig { block_number: 5 }).unwrap();
+		system.set_block_hash([1; 32]);
+		assert_eq!(system.block_number(), 5);
+		assert_eq!(system.parent_hash(), [1; 32]);
+
 		system.inc_block_number();
 		system.inc_nonce(&"alice".to_string());
 
-		assert_eq!(system.block_number(), 1);
-		assert_eq!(system.nonce.get("alice"), Some(&1));
-		assert_eq!(system.nonce.get(&"bob".to_string()), None);
+		assert_eq!(system.block_number(), 6);
+		assert_eq!(system.genesis_hash(), [1; 32]);
+		assert_eq!(system.block_hash(5), Some([1; 32]));
+		assert_eq!(system.block_hash(6), None);
+		assert_eq!(system.nonce(&"alice".to_string()), 1);
+		assert_eq!(system.nonce(&"bob".to_string()), 0);
+		assert_eq!(
+			system.account_nonce().iter().collect::<Vec<_>>(),
+			vec![("alice".to_string(), 1)]
+		);
+	}
+
+	#[test]
+	fn validate_nonces() {
+		use crate::support::InvalidTransaction;
+
+		let mut system = super::SystemPallet::<TestConfig>::new(Storage::new());
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		system.inc_nonce(&alice);
+
+		assert_eq!(system.validate_nonces([(&alice, 1), (&bob, 0), (&alice, 2)]), Ok(()));
+		assert_eq!(
+			system.validate_nonces([(&alice, 1), (&alice, 1)]),
+			Err((1, InvalidTransaction::Stale))
+		);
+		assert_eq!(
+			system.validate_nonces([(&bob, 0), (&alice, 3)]),
+			Err((1, InvalidTransaction::Future))
+		);
+	}
+
+	#[test]
+	fn validate_mortality() {
+		use crate::support::InvalidTransaction;
+
+		let mut system = super::SystemPallet::<TestConfig>::new(Storage::new());
+		system.set_block_hash([0; 32]);
+		for number in 1..256u32 {
+			system.inc_block_number();
+			system.set_block_hash([number as u8; 32]);
+		}
+
+		// Extrinsics can be included in the `MORTALITY_PERIOD` blocks following their birth...
+		assert_eq!(system.validate_mortality(0), Ok([0; 32]));
+		assert_eq!(system.validate_mortality(255), Ok([255; 32]));
+		system.inc_block_number();
+		system.set_block_hash([1; 32]);
+		assert_eq!(system.validate_mortality(0), Err(InvalidTransaction::AncientBirthBlock));
+		assert_eq!(system.validate_mortality(1), Ok([1; 32]));
+
+		// ...and must be born at a known block.
+		assert_eq!(system.validate_mortality(257), Err(InvalidTransaction::AncientBirthBlock));
+	}
+
+	#[test]
+	fn events_by_extrinsic() {
+		let mut system = super::SystemPallet::<TestConfig>::new(Storage::new());
+		system.deposit_event(Phase::ApplyExtrinsic(0), "first");
+		system.deposit_event(Phase::ApplyExtrinsic(1), "second");
+		system.deposit_event(Phase::ApplyExtrinsic(1), "third");
+		system.deposit_event(Phase::Finalization, "fourth");
+
+		assert_eq!(system.extrinsic_events(1).collect::<Vec<_>>(), vec![&"second", &"third"]);
+		assert_eq!(system.events().len(), 4);
+
+		system.reset_events();
+		assert!(system.events().is_empty());
 	}
 }
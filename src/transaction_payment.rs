@@ -0,0 +1,89 @@
+//! Transaction payment, charging the caller of every extrinsic a fee before it is dispatched.
+//!
+//! The fee of an extrinsic is `weight * FEE_MULTIPLIER + length * LENGTH_FEE`, where `weight` is
+//! the weight declared by its call and `length` is the length of the encoded extrinsic in bytes.
+//! The fee is withdrawn from the balance of the caller, and the part paid for weight the call did
+//! not use is refunded once it has been dispatched. Fees are not paid to anyone, they are burned.
+
+use crate::{
+	support::{
+		ChargeTransaction, Currency, DispatchInfo, ExistenceRequirement, InvalidTransaction,
+		PostDispatchInfo, Weight,
+	},
+	types::{
+		BalancesConfig, Runtime,
+		types::{AccountId, Balance},
+	},
+};
+use num::traits::{CheckedAdd, CheckedMul};
+
+pub trait Config: BalancesConfig {
+	/// The fee for each unit of weight.
+	const FEE_MULTIPLIER: Self::Balance;
+	/// The fee for each byte of an encoded extrinsic.
+	const LENGTH_FEE: Self::Balance;
+}
+
+impl Config for Runtime {
+	const FEE_MULTIPLIER: Balance = 1;
+	const LENGTH_FEE: Balance = 10;
+}
+
+/// The fee for `weight`, or `None` if it overflows.
+pub fn weight_fee<T: Config>(weight: Weight) -> Option<T::Balance> {
+	T::Balance::from(weight).checked_mul(&T::FEE_MULTIPLIER)
+}
+
+/// The fee of an extrinsic of `len` bytes declaring `weight`, or `None` if it overflows.
+pub fn compute_fee<T: Config>(len: u32, weight: Weight) -> Option<T::Balance> {
+	let length_fee = T::Balance::from(len as u64).checked_mul(&T::LENGTH_FEE)?;
+	weight_fee::<T>(weight)?.checked_add(&length_fee)
+}
+
+/// Fees are paid from the balances pallet.
+impl ChargeTransaction for Runtime {
+	type Caller = AccountId;
+
+	fn withdraw_fee(
+		&mut self,
+		who: &Self::Caller,
+		info: &DispatchInfo,
+		len: u32,
+	) -> Result<(), InvalidTransaction> {
+		let fee = compute_fee::<Self>(len, info.weight).ok_or(InvalidTransaction::Payment)?;
+		// Fees may not reap the caller, whose nonce is bumped once the fee is paid.
+		self.balances
+			.withdraw(who, fee, ExistenceRequirement::KeepAlive)
+			.map_err(|_| InvalidTransaction::Payment)
+	}
+
+	fn refund_fee(
+		&mut self,
+		who: &Self::Caller,
+		info: &DispatchInfo,
+		post_info: &PostDispatchInfo,
+	) {
+		let unused_weight = info.weight - post_info.calc_actual_weight(info);
+		// The refund is part of the fee withdrawn before dispatching, so computing it can not
+		// overflow. Depositing it only fails if the call itself filled up the balance of `who`, or
+		// reaped `who` and the refund is below the existential deposit, in which case there is
+		// nothing to refund into.
+		if let Some(refund) = weight_fee::<Self>(unused_weight) {
+			let _ = self.balances.deposit_creating(who, refund);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{compute_fee, weight_fee};
+	use crate::types::Runtime;
+
+	#[test]
+	fn fees() {
+		assert_eq!(weight_fee::<Runtime>(10_000), Some(10_000));
+		assert_eq!(compute_fee::<Runtime>(0, 10_000), Some(10_000));
+		assert_eq!(compute_fee::<Runtime>(100, 10_000), Some(11_000));
+		assert_eq!(compute_fee::<Runtime>(100, 0), Some(1_000));
+	}
+}
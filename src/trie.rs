@@ -0,0 +1,218 @@
+//! A binary Merkle trie committing to the key-value storage of the runtime.
+//!
+//! Entries are placed in the trie following the bits of the hash of their key, most significant
+//! bit first. A subtree without entries is represented by `EMPTY`, a subtree holding a single entry
+//! by the hash of that entry (its leaf), and any other subtree by the hash of its two children.
+//! This keeps the trie small, while still giving every key a unique path from the root.
+//!
+//! The value of a key, or its absence, can be proven with the siblings of the nodes along its path.
+
+use crate::crypto::{H256, blake2_256};
+
+/// The hash of a subtree without any entries.
+pub const EMPTY: H256 = [0; 32];
+
+/// The hash of the leaf for an entry, given the hash of its key and of its value.
+///
+/// Leaves and branches are prefixed with different bytes, so a leaf can never be mistaken for a
+/// branch.
+fn leaf_hash(key_hash: &H256, value_hash: &H256) -> H256 {
+	blake2_256(&[&[0][..], key_hash, value_hash].concat())
+}
+
+/// The hash of a branch with the given children.
+fn branch_hash(left: &H256, right: &H256) -> H256 {
+	blake2_256(&[&[1][..], left, right].concat())
+}
+
+/// The number of bits in a path, which bounds the depth of the trie.
+const MAX_DEPTH: usize = 8 * core::mem::size_of::<H256>();
+
+/// Whether the bit at `depth` of `path` is set, i.e. whether the path goes right at that depth.
+fn bit(path: &H256, depth: usize) -> bool {
+	path[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+/// The root of the trie holding `entries`, which must have distinct keys.
+pub fn root<'a>(entries: impl IntoIterator<Item = (&'a [u8], &'a [u8])>) -> H256 {
+	subtree_root(&leaves(entries), 0)
+}
+
+/// The path and value hash of every entry, sorted by path.
+fn leaves<'a>(entries: impl IntoIterator<Item = (&'a [u8], &'a [u8])>) -> Vec<(H256, H256)> {
+	let mut leaves = entries
+		.into_iter()
+		.map(|(key, value)| (blake2_256(key), blake2_256(value)))
+		.collect::<Vec<_>>();
+	leaves.sort();
+	leaves
+}
+
+/// The root of the subtree at `depth` holding `leaves`, sorted by path.
+fn subtree_root(leaves: &[(H256, H256)], depth: usize) -> H256 {
+	match leaves {
+		[] => EMPTY,
+		[(path, value_hash)] => leaf_hash(path, value_hash),
+		_ => {
+			let split = leaves.partition_point(|(path, _)| !bit(path, depth));
+			let left = subtree_root(&leaves[..split], depth + 1);
+			let right = subtree_root(&leaves[split..], depth + 1);
+			branch_hash(&left, &right)
+		},
+	}
+}
+
+/// A proof of the value of a key in a trie, or of its absence.
+#[derive(Debug, Clone, PartialEq, Eq, macros::Encode, macros::Decode)]
+pub struct Proof {
+	/// The value of the key, or `None` if the key is not in the trie.
+	pub value: Option<Vec<u8>>,
+	/// The siblings of the nodes along the path of the key, from the root down.
+	pub siblings: Vec<H256>,
+	/// The path and value hash of the leaf found at the end of the path of an absent key, if the
+	/// path does not end in an empty subtree.
+	pub other_leaf: Option<(H256, H256)>,
+}
+
+/// Prove the value of `key` in the trie holding `entries`, or its absence.
+pub fn prove<'a>(entries: impl IntoIterator<Item = (&'a [u8], &'a [u8])>, key: &[u8]) -> Proof {
+	let entries = entries.into_iter().collect::<Vec<_>>();
+	let value = entries.iter().find(|(other, _)| *other == key).map(|(_, value)| value.to_vec());
+	let leaves = leaves(entries);
+	let path = blake2_256(key);
+
+	// Follow the path down to a subtree with at most one leaf, collecting the siblings.
+	let mut subtree = &leaves[..];
+	let mut siblings = Vec::new();
+	while subtree.len() > 1 {
+		let depth = siblings.len();
+		let (left, right) =
+			subtree.split_at(subtree.partition_point(|(path, _)| !bit(path, depth)));
+		if bit(&path, depth) {
+			siblings.push(subtree_root(left, depth + 1));
+			subtree = right;
+		} else {
+			siblings.push(subtree_root(right, depth + 1));
+			subtree = left;
+		}
+	}
+	let other_leaf = subtree.first().copied().filter(|(other, _)| *other != path);
+
+	Proof { value, siblings, other_leaf }
+}
+
+/// Check that `proof` proves the value of `key`, or its absence, in the trie with the given root.
+pub fn verify(root: &H256, key: &[u8], proof: &Proof) -> bool {
+	// Paths run out of bits past this depth, so no valid proof has more siblings.
+	if proof.siblings.len() > MAX_DEPTH {
+		return false;
+	}
+	let path = blake2_256(key);
+	let mut node = match (&proof.value, &proof.other_leaf) {
+		(Some(value), None) => leaf_hash(&path, &blake2_256(value)),
+		(None, None) => EMPTY,
+		(None, Some((other, value_hash))) => {
+			// The other leaf must be for another key, whose path starts the same way.
+			let shares_path =
+				(0..proof.siblings.len()).all(|depth| bit(other, depth) == bit(&path, depth));
+			if *other == path || !shares_path {
+				return false;
+			}
+			leaf_hash(other, value_hash)
+		},
+		(Some(_), Some(_)) => return false,
+	};
+	for (depth, sibling) in proof.siblings.iter().enumerate().rev() {
+		node = if bit(&path, depth) {
+			branch_hash(sibling, &node)
+		} else {
+			branch_hash(&node, sibling)
+		};
+	}
+	node == *root
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{EMPTY, MAX_DEPTH, Proof, blake2_256, branch_hash, leaf_hash, prove, root, verify};
+
+	#[test]
+	fn roots() {
+		let entries: [(&[u8], &[u8]); 3] = [(b"a", b"1"), (b"b", b"2"), (b"c", b"3")];
+
+		assert_eq!(root([]), EMPTY);
+		assert_eq!(root([entries[0]]), leaf_hash(&blake2_256(b"a"), &blake2_256(b"1")));
+		// The root does not depend on the order of the entries, only on their content.
+		assert_eq!(root(entries), root([entries[2], entries[0], entries[1]]));
+		assert_ne!(root(entries), root([entries[0], entries[1], (b"c", b"4")]));
+		assert_ne!(root(entries), root([entries[0], entries[1]]));
+	}
+
+	#[test]
+	fn entries_split_on_the_first_differing_bit() {
+		// Find a key whose path goes left at the root, and one whose path goes right.
+		let keys = (0u8..).map(|i| [i]).take(16).collect::<Vec<_>>();
+		let goes_right = |key: &[u8; 1]| blake2_256(key)[0] & 0x80 != 0;
+		let left = keys.iter().find(|key| !goes_right(key)).unwrap();
+		let right = keys.iter().find(|key| goes_right(key)).unwrap();
+
+		let leaf = |key: &[u8; 1]| leaf_hash(&blake2_256(key), &blake2_256(b"value"));
+		let entries = [(&left[..], &b"value"[..]), (&right[..], &b"value"[..])];
+		assert_eq!(root(entries), branch_hash(&leaf(left), &leaf(right)));
+	}
+
+	#[test]
+	fn proofs() {
+		let keys = (0u8..20).map(|i| [i]).collect::<Vec<_>>();
+		let entries = keys.iter().map(|key| (&key[..], &key[..])).collect::<Vec<_>>();
+		let root = root(entries.clone());
+
+		for (key, value) in &entries {
+			let proof = prove(entries.clone(), key);
+			assert_eq!(proof.value.as_deref(), Some(*value));
+			assert!(verify(&root, key, &proof));
+			assert!(!verify(&root, b"other", &proof));
+
+			// The value can not be changed, or claimed to be absent.
+			let mut tampered = proof.clone();
+			tampered.value = Some(b"other".to_vec());
+			assert!(!verify(&root, key, &tampered));
+			tampered.value = None;
+			assert!(!verify(&root, key, &tampered));
+		}
+
+		// Absent keys end either in an empty subtree or at the leaf of another key.
+		let absent = (20u8..40).map(|i| [i]).collect::<Vec<_>>();
+		let proofs = absent.iter().map(|key| prove(entries.clone(), key)).collect::<Vec<_>>();
+		assert!(proofs.iter().any(|proof| proof.other_leaf.is_none()));
+		assert!(proofs.iter().any(|proof| proof.other_leaf.is_some()));
+		for (key, proof) in absent.iter().zip(&proofs) {
+			assert_eq!(proof.value, None);
+			assert!(verify(&root, key, proof));
+
+			let mut tampered = proof.clone();
+			tampered.value = Some(b"value".to_vec());
+			assert!(!verify(&root, key, &tampered));
+		}
+
+		// A sibling can not be changed.
+		let mut tampered = prove(entries.clone(), &keys[0]);
+		tampered.siblings[0][0] ^= 1;
+		assert!(!verify(&root, &keys[0], &tampered));
+
+		// Any key is absent from the empty trie.
+		assert!(verify(&EMPTY, b"a", &prove([], b"a")));
+	}
+
+	#[test]
+	fn proofs_deeper_than_the_paths_are_rejected() {
+		let siblings = vec![EMPTY; MAX_DEPTH + 1];
+		let other_leaf = Some((blake2_256(b"b"), blake2_256(b"2")));
+		for proof in [
+			Proof { value: Some(b"1".to_vec()), siblings: siblings.clone(), other_leaf: None },
+			Proof { value: None, siblings, other_leaf },
+		] {
+			assert!(!verify(&EMPTY, b"a", &proof));
+		}
+	}
+}
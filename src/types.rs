@@ -1,56 +1,132 @@
-use num::traits::{CheckedAdd, CheckedSub, One, Zero};
-use std::collections::BTreeMap;
+use num::traits::{CheckedAdd, CheckedMul, CheckedSub, One, Saturating, Zero};
 use crate::balances;
 use crate::proof_of_existence;
+use crate::sudo;
+use crate::system;
+use crate::utility;
+use crate::codec::{Decode, Encode};
+use crate::crypto::H256;
+use crate::storage::Storage;
+use crate::support::{PalletInfo, RawOrigin, TransactionalVec, Weight};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
 
+#[allow(clippy::module_inception)]
 pub mod types {
-	pub type AccountId = String;
+	pub type AccountId = crate::crypto::AccountId32;
+	pub type Signature = crate::crypto::MultiSignature;
 	pub type Balance = u128;
 	pub type Nonce = u32;
 	pub type BlockNumber = u32;
-	pub type Extrinsic = crate::support::Extrinsic<AccountId, super::RuntimeCall>;
+	pub type UncheckedExtrinsic = crate::support::UncheckedExtrinsic<
+		AccountId,
+		Nonce,
+		BlockNumber,
+		super::RuntimeCall,
+		Signature,
+	>;
+	pub type RuntimeOrigin = crate::support::RawOrigin<AccountId>;
 	pub type Header = crate::support::Header<BlockNumber>;
-	pub type Block = crate::support::Block<Header, Extrinsic>;
-	pub type Content = String;
+	pub type Block = crate::support::Block<Header, UncheckedExtrinsic>;
 }
 
 // Main
 #[derive(Debug)]
+#[macros::runtime]
 pub struct Runtime {
 	pub system: SystemPallet<Self>,
 	pub balances: BalancesPallet<Self>,
 	pub proof_of_existence: proof_of_existence::Pallet<Self>,
+	pub sudo: sudo::Pallet<Self>,
+	pub utility: utility::Pallet<Self>,
+}
+
+impl proof_of_existence::Config for Runtime {
+	type Hashing = crate::crypto::BlakeTwo256;
+	type Currency = BalancesPallet<Self>;
+	const CLAIM_DEPOSIT: types::Balance = 10_000;
+	const MAX_DESCRIPTION_LEN: u32 = 256;
+	/// Removing them takes at most half of the weight of a block.
+	const MAX_EXPIRIES_PER_BLOCK: u32 = 50;
+	const MAX_EXPIRY: types::BlockNumber = 100_000;
 }
 
-pub enum RuntimeCall {
-	Balances(balances::Call<Runtime>),
-	// BalancesTransfer { to: types::AccountId, amount: types::Balance },
-	ProofOfExistence(proof_of_existence::Call<Runtime>),
+impl sudo::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
 }
 
-impl proof_of_existence::Config for Runtime {
-	type Content = types::Content;
+impl utility::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	const MAX_CALLS: u32 = 100;
 }
 
 // Balances
-#[derive(Debug)]
 pub struct BalancesPallet<T: BalancesConfig> {
-	pub balances: BTreeMap<T::AccountId, T::Balance>,
+	/// The storage of the runtime, holding the storage items of this pallet.
+	pub storage: Storage,
+	/// The events deposited during the current extrinsic.
+	pub events: TransactionalVec<balances::Event<T>>,
 }
 
 pub trait BalancesConfig: SystemConfig {
-	type Balance: Zero + CheckedSub + CheckedAdd + Copy;
+	type Balance: Zero
+		+ CheckedSub
+		+ CheckedAdd
+		+ CheckedMul
+		+ Saturating
+		+ From<u64>
+		+ Copy
+		+ Ord
+		+ Encode
+		+ Decode
+		+ DeserializeOwned;
+	/// The least balance an account must hold to exist. Accounts dropping below it are reaped.
+	const EXISTENTIAL_DEPOSIT: Self::Balance;
 }
 
 //  System
-#[derive(Debug)]
 pub struct SystemPallet<T: SystemConfig> {
-	pub block_number: T::BlockNumber,
-	pub nonce: BTreeMap<T::AccountId, T::Nonce>,
+	/// The storage of the runtime, holding the storage items of this pallet.
+	pub storage: Storage,
+	/// The hash of every executed block, starting with the genesis block which identifies the chain.
+	///
+	/// These are not part of the state, since the hash of a block depends on its state root.
+	pub block_hash: BTreeMap<T::BlockNumber, H256>,
+	/// The events emitted in the current block.
+	pub events: Vec<EventRecord<T::RuntimeEvent>>,
+}
+
+/// The part of a block which emitted an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+	/// The pallets initializing the block, before its extrinsics are dispatched.
+	Initialization,
+	/// The extrinsic with the given index.
+	ApplyExtrinsic(u32),
+	/// The pallets finalizing the block, once its extrinsics have been dispatched.
+	Finalization,
+}
+
+/// An event emitted in a block, along with the part of the block which emitted it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventRecord<E> {
+	pub phase: Phase,
+	pub event: E,
 }
 
-pub trait SystemConfig {
-	type AccountId: Ord + Clone;
-	type BlockNumber: Zero + One + CheckedAdd + Copy;
-	type Nonce: Zero + One + Copy;
+pub trait SystemConfig: 'static {
+	/// Information about the pallets included in the runtime, used to identify pallet errors.
+	type PalletInfo: PalletInfo;
+	/// The aggregated event type of the runtime.
+	type RuntimeEvent;
+	/// The origin of calls, which can be turned into a `RawOrigin` to check who made them.
+	type RuntimeOrigin: Into<RawOrigin<Self::AccountId>> + From<RawOrigin<Self::AccountId>>;
+	type AccountId: Ord + Clone + Encode + Decode + DeserializeOwned;
+	type BlockNumber: Zero + One + CheckedAdd + Copy + Ord + Encode + Decode + DeserializeOwned;
+	type Nonce: Zero + One + Copy + Ord + Encode + Decode;
+	/// The most weight the extrinsics of a block may use.
+	const MAXIMUM_BLOCK_WEIGHT: Weight;
+	/// The number of blocks following the block an extrinsic was signed at, in which it can be
+	/// included.
+	const MORTALITY_PERIOD: Self::BlockNumber;
 }
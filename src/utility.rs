@@ -0,0 +1,357 @@
+//! The utility pallet, dispatching a batch of calls in a single extrinsic.
+//!
+//! Every call of a batch is dispatched with the origin of the batch itself, e.g. to make many
+//! transfers from one account in one extrinsic. The batch calls differ in how they handle a failing
+//! call: `batch` stops at it, `batch_all` undoes the whole batch, and `force_batch` carries on.
+
+use crate::{
+	codec::{Decode, Encode},
+	storage::Storage,
+	support::{
+		Dispatch, DispatchError, DispatchErrorWithPostInfo, DispatchResult,
+		DispatchResultWithPostInfo, GetDispatchInfo, GetPallet, Hooks, PostDispatchInfo, RawOrigin,
+		Transactional, TransactionalVec, Weight, WithPostDispatchInfo,
+	},
+	types::SystemConfig,
+};
+use core::{fmt::Debug, marker::PhantomData};
+
+pub trait Config:
+	SystemConfig
+	+ Sized
+	+ GetPallet<Pallet<Self>>
+	+ Transactional
+	+ Dispatch<Origin = <Self as SystemConfig>::RuntimeOrigin, Call = <Self as Config>::RuntimeCall>
+{
+	/// The aggregated call type of the runtime, which can be dispatched in a batch.
+	type RuntimeCall: GetDispatchInfo + Clone + Encode + Decode;
+	/// The most calls a batch can hold.
+	const MAX_CALLS: u32;
+}
+
+/// The utility pallet.
+pub struct Pallet<T: Config> {
+	/// The storage of the runtime, holding the storage items of this pallet.
+	pub storage: Storage,
+	/// The events deposited during the current extrinsic.
+	pub events: TransactionalVec<Event<T>>,
+}
+
+/// The genesis configuration of the utility pallet, which has no genesis state.
+#[derive(serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenesisConfig<T: Config> {
+	#[serde(skip)]
+	_config: PhantomData<T>,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+	fn default() -> Self {
+		Self { _config: PhantomData }
+	}
+}
+
+/// The weight of a batch on top of the weight of its calls.
+const BATCH_WEIGHT: Weight = 10_000;
+
+/// The weight declared by a batch of `calls`, which is the most it may use.
+fn batch_weight<C: GetDispatchInfo>(calls: &[C]) -> Weight {
+	calls
+		.iter()
+		.map(|call| call.get_dispatch_info().weight)
+		.fold(BATCH_WEIGHT, Weight::saturating_add)
+}
+
+impl<T: Config> Pallet<T> {
+	pub fn new(storage: Storage) -> Self {
+		Self { storage, events: TransactionalVec::new() }
+	}
+
+	/// Set up the genesis state of the pallet, of which there is none.
+	pub fn build_genesis(&mut self, _config: &GenesisConfig<T>) -> DispatchResult {
+		Ok(())
+	}
+
+	/// Dispatch `call` on behalf of `origin`, returning the weight it actually used along with the
+	/// error it failed with, if any.
+	fn dispatch_item(
+		runtime: &mut T,
+		origin: RawOrigin<T::AccountId>,
+		call: T::RuntimeCall,
+	) -> (Weight, Option<DispatchError>) {
+		let info = call.get_dispatch_info();
+		let (error, post_info) = match runtime.dispatch(origin.into(), call) {
+			Ok(post_info) => (None, post_info),
+			Err(error) => (Some(error.error), error.post_info),
+		};
+		(post_info.calc_actual_weight(&info), error)
+	}
+
+	/// Ensure a batch of `calls` holds at most `MAX_CALLS` calls, before any of them is dispatched.
+	fn ensure_batch_len(calls: &[T::RuntimeCall]) -> Result<(), DispatchErrorWithPostInfo> {
+		if calls.len() > T::MAX_CALLS as usize {
+			return Err(Error::<T>::TooManyCalls.with_weight(BATCH_WEIGHT));
+		}
+		Ok(())
+	}
+}
+
+#[macros::error]
+pub enum Error<T: Config> {
+	/// The batch holds more than `MAX_CALLS` calls.
+	TooManyCalls,
+}
+
+#[macros::event]
+pub enum Event<T: Config> {
+	/// A call of a batch was dispatched successfully.
+	ItemCompleted,
+	/// A call of a batch failed with `error`, and the batch carried on.
+	ItemFailed { error: DispatchError },
+	/// The call at `index` of a batch failed with `error`, so the calls after it were not
+	/// dispatched.
+	BatchInterrupted { index: u32, error: DispatchError },
+	/// Every call of a batch was dispatched successfully.
+	BatchCompleted,
+	/// Every call of a batch was dispatched, but some of them failed.
+	BatchCompletedWithErrors,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Dispatch `calls` one after the other on behalf of the caller, stopping at the first one
+	/// which fails.
+	///
+	/// This succeeds even if a call fails, reporting it in `Event::BatchInterrupted`. The calls
+	/// dispatched before it keep their changes. Batches of more than `MAX_CALLS` calls fail before
+	/// dispatching any of them, as do the other batch calls.
+	#[weight(batch_weight(calls))]
+	pub fn batch(
+		runtime: &mut T,
+		origin: T::RuntimeOrigin,
+		calls: Vec<T::RuntimeCall>,
+	) -> DispatchResultWithPostInfo {
+		Self::ensure_batch_len(&calls)?;
+		let origin: RawOrigin<T::AccountId> = origin.into();
+		let mut weight = BATCH_WEIGHT;
+		for (index, call) in calls.into_iter().enumerate() {
+			let (used, error) = Self::dispatch_item(runtime, origin.clone(), call);
+			weight = weight.saturating_add(used);
+			let pallet: &mut Self = runtime.pallet();
+			match error {
+				None => pallet.deposit_event(Event::ItemCompleted),
+				Some(error) => {
+					pallet.deposit_event(Event::BatchInterrupted { index: index as u32, error });
+					return Ok(PostDispatchInfo { actual_weight: Some(weight) });
+				},
+			}
+		}
+		runtime.pallet().deposit_event(Event::BatchCompleted);
+		Ok(PostDispatchInfo { actual_weight: Some(weight) })
+	}
+
+	/// Dispatch `calls` one after the other on behalf of the caller, all or nothing.
+	///
+	/// If any call fails, the batch fails with its error, undoing the calls dispatched before it.
+	#[weight(batch_weight(calls))]
+	pub fn batch_all(
+		runtime: &mut T,
+		origin: T::RuntimeOrigin,
+		calls: Vec<T::RuntimeCall>,
+	) -> DispatchResultWithPostInfo {
+		Self::ensure_batch_len(&calls)?;
+		let origin: RawOrigin<T::AccountId> = origin.into();
+		runtime.with_transaction(|runtime| {
+			let mut weight = BATCH_WEIGHT;
+			for call in calls {
+				let (used, error) = Self::dispatch_item(runtime, origin.clone(), call);
+				weight = weight.saturating_add(used);
+				if let Some(error) = error {
+					return Err(error.with_weight(weight));
+				}
+				runtime.pallet().deposit_event(Event::ItemCompleted);
+			}
+			runtime.pallet().deposit_event(Event::BatchCompleted);
+			Ok(PostDispatchInfo { actual_weight: Some(weight) })
+		})
+	}
+
+	/// Dispatch `calls` one after the other on behalf of the caller, carrying on past the ones
+	/// which fail.
+	///
+	/// This succeeds even if calls fail, reporting each of them in `Event::ItemFailed`.
+	#[weight(batch_weight(calls))]
+	pub fn force_batch(
+		runtime: &mut T,
+		origin: T::RuntimeOrigin,
+		calls: Vec<T::RuntimeCall>,
+	) -> DispatchResultWithPostInfo {
+		Self::ensure_batch_len(&calls)?;
+		let origin: RawOrigin<T::AccountId> = origin.into();
+		let mut weight = BATCH_WEIGHT;
+		let mut failed = false;
+		for call in calls {
+			let (used, error) = Self::dispatch_item(runtime, origin.clone(), call);
+			weight = weight.saturating_add(used);
+			let pallet: &mut Self = runtime.pallet();
+			match error {
+				None => pallet.deposit_event(Event::ItemCompleted),
+				Some(error) => {
+					failed = true;
+					pallet.deposit_event(Event::ItemFailed { error });
+				},
+			}
+		}
+		let event = if failed { Event::BatchCompletedWithErrors } else { Event::BatchCompleted };
+		runtime.pallet().deposit_event(event);
+		Ok(PostDispatchInfo { actual_weight: Some(weight) })
+	}
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> Debug for Pallet<T> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("Pallet").field("events", &self.events).finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Event;
+	use crate::{
+		balances,
+		codec::{self, Decode, Encode},
+		crypto::Keyring,
+		support::{
+			Dispatch, DispatchError, GetDispatchInfo, MAX_EXTRINSIC_DEPTH, RawOrigin,
+			WithPostDispatchInfo,
+		},
+		types::{Runtime, RuntimeCall},
+	};
+
+	fn transfer(to: Keyring, amount: u128) -> RuntimeCall {
+		RuntimeCall::Balances(balances::Call::Transfer { to: to.to_account_id(), amount })
+	}
+
+	/// Transfers from Alice, the second of which she can not afford.
+	fn transfers() -> Vec<RuntimeCall> {
+		vec![
+			transfer(Keyring::Bob, 10_000),
+			transfer(Keyring::Charlie, 200_000),
+			transfer(Keyring::Bob, 10_000),
+		]
+	}
+
+	/// A runtime where Alice has a balance of 100_000.
+	fn new_runtime() -> Runtime {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(&Keyring::Alice.to_account_id(), 100_000).unwrap();
+		runtime
+	}
+
+	fn insufficient_balance() -> DispatchError {
+		balances::Error::<Runtime>::InsufficientBalance.into()
+	}
+
+	#[test]
+	fn batch_stops_at_the_first_failure() {
+		let mut runtime = new_runtime();
+		let alice = RawOrigin::Signed(Keyring::Alice.to_account_id());
+		let call = RuntimeCall::Utility(super::Call::Batch { calls: transfers() });
+		// The weight of a batch includes the weight of all of its calls.
+		assert_eq!(call.get_dispatch_info().weight, 40_000);
+
+		// The batch succeeds, keeping the changes of the calls before the failing one, and only
+		// uses the weight of the calls it dispatched.
+		let post_info = runtime.dispatch(alice, call).unwrap();
+		assert_eq!(post_info.actual_weight, Some(30_000));
+		assert_eq!(runtime.balances.balance(&Keyring::Bob.to_account_id()), 10_000);
+		assert_eq!(
+			runtime.utility.take_events(),
+			vec![
+				Event::ItemCompleted,
+				Event::BatchInterrupted { index: 1, error: insufficient_balance() }
+			]
+		);
+	}
+
+	#[test]
+	fn batch_all_is_all_or_nothing() {
+		let mut runtime = new_runtime();
+		let alice = RawOrigin::Signed(Keyring::Alice.to_account_id());
+		let bob = Keyring::Bob.to_account_id();
+
+		// A failing call undoes the whole batch.
+		let call = RuntimeCall::Utility(super::Call::BatchAll { calls: transfers() });
+		let result = runtime.dispatch(alice.clone(), call);
+		assert_eq!(result, Err(insufficient_balance().with_weight(30_000)));
+		assert_eq!(runtime.balances.balance(&bob), 0);
+		assert!(runtime.balances.take_events().is_empty());
+		assert!(runtime.utility.take_events().is_empty());
+
+		let calls = vec![transfer(Keyring::Bob, 10_000), transfer(Keyring::Charlie, 20_000)];
+		let call = RuntimeCall::Utility(super::Call::BatchAll { calls });
+		assert!(runtime.dispatch(alice, call).is_ok());
+		assert_eq!(runtime.balances.balance(&bob), 10_000);
+		assert_eq!(runtime.balances.balance(&Keyring::Charlie.to_account_id()), 20_000);
+		assert_eq!(
+			runtime.utility.take_events(),
+			vec![Event::ItemCompleted, Event::ItemCompleted, Event::BatchCompleted]
+		);
+	}
+
+	#[test]
+	fn force_batch_carries_on_past_failures() {
+		let mut runtime = new_runtime();
+		let alice = RawOrigin::Signed(Keyring::Alice.to_account_id());
+
+		// Calls are dispatched with the origin of the batch, so root-only calls fail.
+		let mut calls = transfers();
+		let who = Keyring::Alice.to_account_id();
+		calls.push(RuntimeCall::Balances(balances::Call::ForceSetBalance { who, amount: 0 }));
+		let call = RuntimeCall::Utility(super::Call::ForceBatch { calls });
+		assert!(runtime.dispatch(alice, call).is_ok());
+		assert_eq!(runtime.balances.balance(&Keyring::Bob.to_account_id()), 20_000);
+		assert_eq!(
+			runtime.utility.take_events(),
+			vec![
+				Event::ItemCompleted,
+				Event::ItemFailed { error: insufficient_balance() },
+				Event::ItemCompleted,
+				Event::ItemFailed { error: DispatchError::BadOrigin },
+				Event::BatchCompletedWithErrors,
+			]
+		);
+	}
+
+	#[test]
+	fn batches_are_limited_in_length_and_depth() {
+		let mut runtime = new_runtime();
+		let alice = RawOrigin::Signed(Keyring::Alice.to_account_id());
+
+		// Batches of too many calls fail before dispatching any of them.
+		let calls = vec![transfer(Keyring::Bob, 1_000); 101];
+		for call in [
+			super::Call::Batch { calls: calls.clone() },
+			super::Call::BatchAll { calls: calls.clone() },
+			super::Call::ForceBatch { calls },
+		] {
+			let result = runtime.dispatch(alice.clone(), RuntimeCall::Utility(call));
+			assert_eq!(result, Err(super::Error::<Runtime>::TooManyCalls.with_weight(10_000)));
+		}
+		assert_eq!(runtime.balances.balance(&Keyring::Bob.to_account_id()), 0);
+
+		// Batches nested inside batches count towards the depth limit of calls.
+		let mut call = transfer(Keyring::Bob, 1_000);
+		for _ in 1..MAX_EXTRINSIC_DEPTH {
+			call = RuntimeCall::Utility(super::Call::Batch { calls: vec![call] });
+		}
+		assert!(RuntimeCall::decode_all(&call.encode()).is_ok());
+		let call = RuntimeCall::Utility(super::Call::Batch { calls: vec![call] });
+		assert_eq!(
+			RuntimeCall::decode_all(&call.encode()).err(),
+			Some(codec::Error::Invalid("value nested too deeply"))
+		);
+	}
+}